@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// A file or folder matched by a search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub name: String,
+    pub path: PathBuf,
+    /// The extension, or `folder`/`file` when there is none.
+    pub class: String,
+    pub size: u64,
+    /// Last modification as Unix seconds, 0 when unknown.
+    pub modified: i64,
+    pub is_dir: bool,
+    /// How often it was opened from the app.
+    #[serde(default)]
+    pub run_count: u64,
+    /// Last open from the app as Unix seconds, 0 when never opened.
+    #[serde(default)]
+    pub date_run: i64,
+}
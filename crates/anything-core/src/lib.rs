@@ -0,0 +1,9 @@
+mod hit;
+mod mount;
+pub mod query;
+mod search;
+
+pub use hit::SearchHit;
+pub use mount::{Mount, MountKind, MountPolicy};
+pub use query::QueryError;
+pub use search::{SearchOptions, Sort, SortKey};
@@ -0,0 +1,87 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Where a mounted filesystem keeps its files, which decides how safe it is
+/// to walk and watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MountKind {
+    Local,
+    /// NFS, SMB, sshfs and the like, which can stall a walk for minutes.
+    Network,
+    /// USB drives and cards, which come and go.
+    Removable,
+    /// Kernel and in-memory filesystems such as proc, sysfs or tmpfs.
+    Virtual,
+}
+
+/// What indexing does with a mounted filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MountPolicy {
+    Index,
+    Skip,
+    /// Walked when the index is built, but changes are not followed.
+    IndexWithoutWatching,
+}
+
+impl MountPolicy {
+    pub fn default_for(kind: MountKind) -> Self {
+        match kind {
+            MountKind::Local => MountPolicy::Index,
+            MountKind::Removable => MountPolicy::IndexWithoutWatching,
+            MountKind::Network | MountKind::Virtual => MountPolicy::Skip,
+        }
+    }
+
+    /// The policy after this one, for toggling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            MountPolicy::Index => MountPolicy::IndexWithoutWatching,
+            MountPolicy::IndexWithoutWatching => MountPolicy::Skip,
+            MountPolicy::Skip => MountPolicy::Index,
+        }
+    }
+
+    pub fn is_indexed(self) -> bool {
+        self != MountPolicy::Skip
+    }
+
+    pub fn is_watched(self) -> bool {
+        self == MountPolicy::Index
+    }
+}
+
+impl fmt::Display for MountKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MountKind::Local => "local",
+            MountKind::Network => "network",
+            MountKind::Removable => "removable",
+            MountKind::Virtual => "virtual",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for MountPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MountPolicy::Index => "Index",
+            MountPolicy::Skip => "Skip",
+            MountPolicy::IndexWithoutWatching => "Index without watching",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A mounted filesystem and the policy indexing applies to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub kind: MountKind,
+    pub policy: MountPolicy,
+}
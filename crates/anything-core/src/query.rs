@@ -0,0 +1,751 @@
+use std::{fmt, ops::Bound};
+
+use time::{Date, Duration, Month, OffsetDateTime};
+
+use crate::SearchOptions;
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+const GB: u64 = 1024 * MB;
+const TB: u64 = 1024 * GB;
+
+pub type SizeRange = (Bound<u64>, Bound<u64>);
+pub type CountRange = (Bound<u64>, Bound<u64>);
+pub type DateRange = (Bound<i64>, Bound<i64>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    MissingValue(String),
+    InvalidSize(String),
+    InvalidCount(String),
+    InvalidDate(String),
+    InvalidRegex(String),
+    UnbalancedGroup,
+    UnterminatedQuote,
+    PageOutOfRange,
+    Cancelled,
+    Index(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::MissingValue(name) => write!(f, "modifier '{}:' requires a value", name),
+            QueryError::InvalidSize(value) => write!(f, "invalid size '{}'", value),
+            QueryError::InvalidCount(value) => write!(f, "invalid count '{}'", value),
+            QueryError::InvalidDate(value) => write!(f, "invalid date '{}'", value),
+            QueryError::InvalidRegex(e) => write!(f, "invalid regex: {}", e),
+            QueryError::UnbalancedGroup => write!(f, "unbalanced '<' '>' group"),
+            QueryError::UnterminatedQuote => write!(f, "unterminated quote"),
+            QueryError::PageOutOfRange => write!(f, "offset plus limit is out of range"),
+            QueryError::Cancelled => write!(f, "search cancelled"),
+            QueryError::Index(e) => write!(f, "index error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Term(String),
+    Modifier(Modifier),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Modifier {
+    Ext(Vec<String>),
+    Size(SizeRange),
+    DateModified(DateRange),
+    DateCreated(DateRange),
+    /// How often a file was opened from the app.
+    RunCount(CountRange),
+    /// When a file was last opened from the app.
+    DateRun(DateRange),
+    Path(String),
+    Folder(Option<String>),
+    File(Option<String>),
+    Parent(String),
+    Fuzzy(String),
+    /// Matched against the name, or against the full path when the pattern
+    /// contains a `/`.
+    Regex(String),
+    /// Matches like a plain search term but with exact case.
+    Case(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// Parses an Everything-style query. Whitespace means AND, `|` means OR and
+/// binds tighter than AND, `!` negates and `< >` groups. Quotes keep spaces
+/// and operators in a term, and `""` inside quotes stands for one `"`.
+pub fn parse(query: &str) -> Result<Expr, QueryError> {
+    parse_at(query, OffsetDateTime::now_utc())
+}
+
+pub(crate) fn parse_at(query: &str, now: OffsetDateTime) -> Result<Expr, QueryError> {
+    let tokens = lex(query)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        now,
+    };
+    let expr = parser.parse_and()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(QueryError::UnbalancedGroup);
+    }
+    Ok(expr)
+}
+
+/// Parses a query under the search options. With `regex` the whole query is
+/// one pattern, otherwise `match_case` and `match_path` turn every plain term
+/// into a `case:` or `path:` modifier.
+pub fn parse_with(query: &str, options: SearchOptions) -> Result<Expr, QueryError> {
+    if options.regex {
+        return parse_word(&format!("regex:{}", query), OffsetDateTime::now_utc());
+    }
+    Ok(apply_options(parse(query)?, options))
+}
+
+fn apply_options(expr: Expr, options: SearchOptions) -> Expr {
+    match expr {
+        Expr::Term(text) if options.match_case => Expr::Modifier(Modifier::Case(text)),
+        Expr::Term(text) if options.match_path => Expr::Modifier(Modifier::Path(text)),
+        Expr::Not(inner) => Expr::Not(Box::new(apply_options(*inner, options))),
+        Expr::And(items) => Expr::And(
+            items
+                .into_iter()
+                .map(|item| apply_options(item, options))
+                .collect(),
+        ),
+        Expr::Or(items) => Expr::Or(
+            items
+                .into_iter()
+                .map(|item| apply_options(item, options))
+                .collect(),
+        ),
+        expr => expr,
+    }
+}
+
+fn lex(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = vec![];
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '|' => {
+                chars.next();
+                tokens.push(Token::Or);
+            }
+            '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            '<' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            '>' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                let mut word = String::new();
+                let mut after_colon = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        chars.next();
+                        loop {
+                            match chars.next() {
+                                Some('"') if chars.peek() == Some(&'"') => {
+                                    chars.next();
+                                    word.push('"');
+                                }
+                                Some('"') => break,
+                                Some(c) => word.push(c),
+                                None => return Err(QueryError::UnterminatedQuote),
+                            }
+                        }
+                        after_colon = false;
+                        continue;
+                    }
+                    // comparison operators directly after a modifier belong to its value
+                    if after_colon && matches!(c, '<' | '>' | '=') && takes_comparison(&word) {
+                        chars.next();
+                        word.push(c);
+                        continue;
+                    }
+                    if c.is_whitespace()
+                        || (matches!(c, '|' | '<' | '>') && !takes_raw_value(&word))
+                    {
+                        break;
+                    }
+                    chars.next();
+                    after_colon = c == ':';
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Regex values keep `|`, `<` and `>` so alternations and groups survive.
+fn takes_raw_value(word: &str) -> bool {
+    word.get(..6)
+        .is_some_and(|name| name.eq_ignore_ascii_case("regex:"))
+}
+
+fn takes_comparison(word: &str) -> bool {
+    let name = word
+        .trim_end_matches(['<', '>', '='])
+        .trim_end_matches(':')
+        .to_ascii_lowercase();
+    matches!(
+        name.as_str(),
+        "size" | "dm" | "datemodified" | "dc" | "datecreated" | "runcount" | "dr" | "daterun"
+    )
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    now: OffsetDateTime,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut items = vec![];
+        while let Some(token) = self.peek() {
+            if *token == Token::Close {
+                break;
+            }
+            items.push(self.parse_or()?);
+        }
+        if items.len() == 1 {
+            Ok(items.remove(0))
+        } else {
+            Ok(Expr::And(items))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut items = vec![self.parse_unary()?];
+        while let Some(Token::Or) = self.peek() {
+            self.pos += 1;
+            items.push(self.parse_unary()?);
+        }
+        if items.len() == 1 {
+            Ok(items.remove(0))
+        } else {
+            Ok(Expr::Or(items))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Open) => {
+                self.pos += 1;
+                let expr = self.parse_and()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(QueryError::UnbalancedGroup);
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Word(word)) => {
+                self.pos += 1;
+                parse_word(&word, self.now)
+            }
+            Some(Token::Close) => Err(QueryError::UnbalancedGroup),
+            // a dangling operator such as a trailing `|` leaves an empty group
+            Some(Token::Or) | Some(Token::Not) | None => Ok(Expr::And(vec![])),
+        }
+    }
+}
+
+fn parse_word(word: &str, now: OffsetDateTime) -> Result<Expr, QueryError> {
+    let Some((name, value)) = word.split_once(':') else {
+        return Ok(Expr::Term(word.to_string()));
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Ok(Expr::Term(word.to_string()));
+    }
+
+    let name = name.to_ascii_lowercase();
+    let required = |value: &str| -> Result<String, QueryError> {
+        if value.is_empty() {
+            Err(QueryError::MissingValue(name.clone()))
+        } else {
+            Ok(value.to_string())
+        }
+    };
+    let optional = |value: &str| (!value.is_empty()).then(|| value.to_string());
+
+    let modifier = match name.as_str() {
+        "ext" => Modifier::Ext(
+            required(value)?
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+        ),
+        "size" => Modifier::Size(parse_size_range(&required(value)?)?),
+        "dm" | "datemodified" => Modifier::DateModified(parse_date_range(&required(value)?, now)?),
+        "dc" | "datecreated" => Modifier::DateCreated(parse_date_range(&required(value)?, now)?),
+        "runcount" => Modifier::RunCount(parse_count_range(&required(value)?)?),
+        "dr" | "daterun" => Modifier::DateRun(parse_date_range(&required(value)?, now)?),
+        "path" => Modifier::Path(required(value)?),
+        "folder" => Modifier::Folder(optional(value)),
+        "file" => Modifier::File(optional(value)),
+        "parent" => Modifier::Parent(required(value)?),
+        "fuzzy" => Modifier::Fuzzy(required(value)?),
+        "case" => Modifier::Case(required(value)?),
+        "regex" => {
+            let pattern = required(value)?;
+            regex::Regex::new(&pattern).map_err(|e| QueryError::InvalidRegex(e.to_string()))?;
+            Modifier::Regex(pattern)
+        }
+        // like Everything, an unknown prefix is part of the name, e.g. `todo:milk`
+        _ => return Ok(Expr::Term(word.to_string())),
+    };
+    Ok(Expr::Modifier(modifier))
+}
+
+enum Comparison {
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Equal,
+}
+
+fn split_comparison(value: &str) -> (Comparison, &str) {
+    for (prefix, comparison) in [
+        (">=", Comparison::GreaterEqual),
+        ("<=", Comparison::LessEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (comparison, rest);
+        }
+    }
+    (Comparison::Equal, value)
+}
+
+/// Applies a comparison to the half-open interval `[start, end)` a value denotes.
+fn compare_interval(comparison: Comparison, start: i128, end: i128) -> (Bound<i128>, Bound<i128>) {
+    match comparison {
+        Comparison::Greater => (Bound::Included(end), Bound::Unbounded),
+        Comparison::GreaterEqual => (Bound::Included(start), Bound::Unbounded),
+        Comparison::Less => (Bound::Unbounded, Bound::Excluded(start)),
+        Comparison::LessEqual => (Bound::Unbounded, Bound::Excluded(end)),
+        Comparison::Equal => (Bound::Included(start), Bound::Excluded(end)),
+    }
+}
+
+fn parse_size_range(value: &str) -> Result<SizeRange, QueryError> {
+    let invalid = || QueryError::InvalidSize(value.to_string());
+    let to_u64 = |bound: Bound<i128>| -> Result<Bound<u64>, QueryError> {
+        Ok(match bound {
+            Bound::Included(v) => Bound::Included(u64::try_from(v).map_err(|_| invalid())?),
+            Bound::Excluded(v) => Bound::Excluded(u64::try_from(v).map_err(|_| invalid())?),
+            Bound::Unbounded => Bound::Unbounded,
+        })
+    };
+
+    let (lower, upper) = if let Some((from, to)) = value.split_once("..") {
+        let (from, _) = parse_size_interval(from).ok_or_else(invalid)?;
+        let (_, to) = parse_size_interval(to).ok_or_else(invalid)?;
+        (Bound::Included(from), Bound::Excluded(to))
+    } else {
+        let (comparison, rest) = split_comparison(value);
+        let (start, end) = parse_size_interval(rest).ok_or_else(invalid)?;
+        compare_interval(comparison, start, end)
+    };
+    Ok((to_u64(lower)?, to_u64(upper)?))
+}
+
+/// Returns the half-open byte interval a size value denotes, using Everything's
+/// named size buckets.
+fn parse_size_interval(value: &str) -> Option<(i128, i128)> {
+    let value = value.trim().to_lowercase();
+    let bucket = match value.as_str() {
+        "empty" => Some((0, 1)),
+        "tiny" => Some((0, 10 * KB)),
+        "small" => Some((10 * KB, 100 * KB)),
+        "medium" => Some((100 * KB, MB)),
+        "large" => Some((MB, 16 * MB)),
+        "huge" => Some((16 * MB, 128 * MB)),
+        "gigantic" => Some((128 * MB, u64::MAX)),
+        _ => None,
+    };
+    if let Some((start, end)) = bucket {
+        return Some((start as i128, end as i128));
+    }
+
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" => KB,
+        "m" | "mb" => MB,
+        "g" | "gb" => GB,
+        "t" | "tb" => TB,
+        _ => return None,
+    };
+    let bytes = (number * unit as f64) as i128;
+    Some((bytes, bytes + 1))
+}
+
+fn parse_count_range(value: &str) -> Result<CountRange, QueryError> {
+    let invalid = || QueryError::InvalidCount(value.to_string());
+    let parse = |value: &str| value.trim().parse::<u64>().map_err(|_| invalid());
+    let to_u64 = |bound: Bound<i128>| match bound {
+        Bound::Included(v) => Bound::Included(v as u64),
+        Bound::Excluded(v) => Bound::Excluded(v as u64),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+
+    if let Some((from, to)) = value.split_once("..") {
+        return Ok((Bound::Included(parse(from)?), Bound::Included(parse(to)?)));
+    }
+    let (comparison, rest) = split_comparison(value);
+    let count = parse(rest)?;
+    let end = count.checked_add(1).ok_or_else(invalid)?;
+    let (lower, upper) = compare_interval(comparison, count as i128, end as i128);
+    Ok((to_u64(lower), to_u64(upper)))
+}
+
+fn parse_date_range(value: &str, now: OffsetDateTime) -> Result<DateRange, QueryError> {
+    let invalid = || QueryError::InvalidDate(value.to_string());
+    let to_i64 = |bound: Bound<i128>| match bound {
+        Bound::Included(v) => Bound::Included(v as i64),
+        Bound::Excluded(v) => Bound::Excluded(v as i64),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+
+    let (lower, upper) = if let Some((from, to)) = value.split_once("..") {
+        let (from, _) = parse_date_interval(from, now).ok_or_else(invalid)?;
+        let (_, to) = parse_date_interval(to, now).ok_or_else(invalid)?;
+        (Bound::Included(from), Bound::Excluded(to))
+    } else {
+        let (comparison, rest) = split_comparison(value);
+        let (start, end) = parse_date_interval(rest, now).ok_or_else(invalid)?;
+        compare_interval(comparison, start, end)
+    };
+    Ok((to_i64(lower), to_i64(upper)))
+}
+
+/// Returns the half-open interval of unix seconds a date value denotes. Days
+/// are interpreted in UTC.
+fn parse_date_interval(value: &str, now: OffsetDateTime) -> Option<(i128, i128)> {
+    let today = now.date();
+    let week_start = today - Duration::days(today.weekday().number_days_from_monday() as i64);
+    let month_start = Date::from_calendar_date(today.year(), today.month(), 1).ok()?;
+    let year_start = Date::from_calendar_date(today.year(), Month::January, 1).ok()?;
+    let last_month_start = if today.month() == Month::January {
+        Date::from_calendar_date(today.year() - 1, Month::December, 1).ok()?
+    } else {
+        Date::from_calendar_date(today.year(), today.month().previous(), 1).ok()?
+    };
+    let tomorrow = today.next_day()?;
+
+    let (start, end) = match value.trim().to_lowercase().as_str() {
+        "today" => (today, tomorrow),
+        "yesterday" => (today.previous_day()?, today),
+        "thisweek" => (week_start, tomorrow),
+        "lastweek" => (week_start - Duration::weeks(1), week_start),
+        "pastweek" => (today - Duration::weeks(1), tomorrow),
+        "thismonth" => (month_start, tomorrow),
+        "lastmonth" => (last_month_start, month_start),
+        "pastmonth" => (today - Duration::days(30), tomorrow),
+        "thisyear" => (year_start, tomorrow),
+        "lastyear" => (
+            Date::from_calendar_date(today.year() - 1, Month::January, 1).ok()?,
+            year_start,
+        ),
+        "pastyear" => (today - Duration::days(365), tomorrow),
+        other => parse_calendar_interval(other)?,
+    };
+    Some((to_unix(start), to_unix(end)))
+}
+
+/// Parses `YYYY`, `YYYY-MM` or `YYYY-MM-DD` (also with `/` separators).
+fn parse_calendar_interval(value: &str) -> Option<(Date, Date)> {
+    let parts: Vec<&str> = value.split(['-', '/']).collect();
+    let year: i32 = parts.first()?.parse().ok()?;
+    match parts.len() {
+        1 => Some((
+            Date::from_calendar_date(year, Month::January, 1).ok()?,
+            Date::from_calendar_date(year + 1, Month::January, 1).ok()?,
+        )),
+        2 => {
+            let month = Month::try_from(parts[1].parse::<u8>().ok()?).ok()?;
+            let start = Date::from_calendar_date(year, month, 1).ok()?;
+            let end = if month == Month::December {
+                Date::from_calendar_date(year + 1, Month::January, 1).ok()?
+            } else {
+                Date::from_calendar_date(year, month.next(), 1).ok()?
+            };
+            Some((start, end))
+        }
+        3 => {
+            let month = Month::try_from(parts[1].parse::<u8>().ok()?).ok()?;
+            let day = Date::from_calendar_date(year, month, parts[2].parse().ok()?).ok()?;
+            Some((day, day.next_day()?))
+        }
+        _ => None,
+    }
+}
+
+fn to_unix(date: Date) -> i128 {
+    date.midnight().assume_utc().unix_timestamp() as i128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_fixed(query: &str) -> Result<Expr, QueryError> {
+        // a Wednesday
+        let now = Date::from_calendar_date(2025, Month::June, 11)
+            .unwrap()
+            .midnight()
+            .assume_utc();
+        parse_at(query, now)
+    }
+
+    fn unix(date: &str) -> i64 {
+        to_unix(parse_calendar_interval(date).unwrap().0) as i64
+    }
+
+    #[test]
+    fn test_search_options() {
+        let match_case = SearchOptions {
+            match_case: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with("Readme !ext:md", match_case).unwrap(),
+            Expr::And(vec![
+                Expr::Modifier(Modifier::Case("Readme".to_string())),
+                Expr::Not(Box::new(Expr::Modifier(Modifier::Ext(vec![
+                    "md".to_string()
+                ])))),
+            ])
+        );
+
+        let regex = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with("^a|b c$", regex).unwrap(),
+            Expr::Modifier(Modifier::Regex("^a|b c$".to_string()))
+        );
+        assert!(matches!(
+            parse_with("[", regex),
+            Err(QueryError::InvalidRegex(_))
+        ));
+    }
+
+    #[test]
+    fn test_and_or_not_precedence() {
+        let expr = parse_fixed("report !draft pdf|docx").unwrap();
+        assert_eq!(
+            expr,
+            Expr::And(vec![
+                Expr::Term("report".into()),
+                Expr::Not(Box::new(Expr::Term("draft".into()))),
+                Expr::Or(vec![Expr::Term("pdf".into()), Expr::Term("docx".into())]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_groups_and_quotes() {
+        let expr = parse_fixed("<a|b> \"meeting notes\"").unwrap();
+        assert_eq!(
+            expr,
+            Expr::And(vec![
+                Expr::Or(vec![Expr::Term("a".into()), Expr::Term("b".into())]),
+                Expr::Term("meeting notes".into()),
+            ])
+        );
+        assert!(matches!(
+            parse_fixed("<a b"),
+            Err(QueryError::UnbalancedGroup)
+        ));
+        assert!(matches!(
+            parse_fixed("a>"),
+            Err(QueryError::UnbalancedGroup)
+        ));
+        assert!(matches!(
+            parse_fixed("\"a"),
+            Err(QueryError::UnterminatedQuote)
+        ));
+        assert_eq!(
+            parse_fixed("\"a \"\"b c\"").unwrap(),
+            Expr::Term("a \"b c".into())
+        );
+    }
+
+    #[test]
+    fn test_ext_and_path_modifiers() {
+        assert_eq!(
+            parse_fixed("ext:PDF;.docx").unwrap(),
+            Expr::Modifier(Modifier::Ext(vec!["pdf".into(), "docx".into()]))
+        );
+        assert_eq!(
+            parse_fixed("path:\"my projects\"").unwrap(),
+            Expr::Modifier(Modifier::Path("my projects".into()))
+        );
+        assert_eq!(
+            parse_fixed("folder:").unwrap(),
+            Expr::Modifier(Modifier::Folder(None))
+        );
+        assert_eq!(
+            parse_fixed("<file:src>").unwrap(),
+            Expr::Modifier(Modifier::File(Some("src".into())))
+        );
+        assert_eq!(
+            parse_fixed("Bogus:1").unwrap(),
+            Expr::Term("Bogus:1".into())
+        );
+        assert!(matches!(
+            parse_fixed("ext:"),
+            Err(QueryError::MissingValue(_))
+        ));
+        assert_eq!(
+            parse_fixed("fuzzy:recipt").unwrap(),
+            Expr::Modifier(Modifier::Fuzzy("recipt".into()))
+        );
+        assert_eq!(
+            parse_fixed(r"regex:^IMG_\d{4}\.(jpg|heic)$").unwrap(),
+            Expr::Modifier(Modifier::Regex(r"^IMG_\d{4}\.(jpg|heic)$".into()))
+        );
+        assert!(matches!(
+            parse_fixed("regex:(unclosed"),
+            Err(QueryError::InvalidRegex(_))
+        ));
+        assert_eq!(
+            parse_fixed("case:README").unwrap(),
+            Expr::Modifier(Modifier::Case("README".into()))
+        );
+        assert_eq!(parse_fixed("10:30").unwrap(), Expr::Term("10:30".into()));
+    }
+
+    #[test]
+    fn test_size_modifier() {
+        assert_eq!(
+            parse_fixed("size:>10mb").unwrap(),
+            Expr::Modifier(Modifier::Size((
+                Bound::Included(10 * MB + 1),
+                Bound::Unbounded
+            )))
+        );
+        assert_eq!(
+            parse_fixed("size:<=1kb").unwrap(),
+            Expr::Modifier(Modifier::Size((Bound::Unbounded, Bound::Excluded(KB + 1))))
+        );
+        assert_eq!(
+            parse_fixed("size:empty").unwrap(),
+            Expr::Modifier(Modifier::Size((Bound::Included(0), Bound::Excluded(1))))
+        );
+        assert!(matches!(
+            parse_fixed("size:>lots"),
+            Err(QueryError::InvalidSize(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_modifiers() {
+        assert_eq!(
+            parse_fixed("runcount:>2").unwrap(),
+            Expr::Modifier(Modifier::RunCount((Bound::Included(3), Bound::Unbounded)))
+        );
+        assert_eq!(
+            parse_fixed("runcount:1..4").unwrap(),
+            Expr::Modifier(Modifier::RunCount((Bound::Included(1), Bound::Included(4))))
+        );
+        assert_eq!(
+            parse_fixed("dr:today").unwrap(),
+            Expr::Modifier(Modifier::DateRun((
+                Bound::Included(unix("2025-06-11")),
+                Bound::Excluded(unix("2025-06-12"))
+            )))
+        );
+        assert!(matches!(
+            parse_fixed("runcount:often"),
+            Err(QueryError::InvalidCount(_))
+        ));
+        assert!(matches!(
+            parse_fixed("runcount:>18446744073709551615"),
+            Err(QueryError::InvalidCount(_))
+        ));
+    }
+
+    #[test]
+    fn test_date_modifier() {
+        assert_eq!(
+            parse_fixed("dm:lastweek").unwrap(),
+            Expr::Modifier(Modifier::DateModified((
+                Bound::Included(unix("2025-06-02")),
+                Bound::Excluded(unix("2025-06-09"))
+            )))
+        );
+        assert_eq!(
+            parse_fixed("dc:>=2024-02").unwrap(),
+            Expr::Modifier(Modifier::DateCreated((
+                Bound::Included(unix("2024-02-01")),
+                Bound::Unbounded
+            )))
+        );
+        assert_eq!(
+            parse_fixed("dm:2024..2024-06-30").unwrap(),
+            Expr::Modifier(Modifier::DateModified((
+                Bound::Included(unix("2024-01-01")),
+                Bound::Excluded(unix("2024-07-01"))
+            )))
+        );
+        assert!(matches!(
+            parse_fixed("dm:someday"),
+            Err(QueryError::InvalidDate(_))
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// The column hits are ordered by. `Relevance` keeps the ranking order and
+/// ignores the direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Relevance,
+    Kind,
+    Name,
+    Path,
+    Size,
+    Modified,
+    /// How often the file was opened from the app.
+    RunCount,
+    /// When the file was last opened from the app.
+    DateRun,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+/// Switches applied to the whole query, like Everything's search menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Plain terms match names with exact case.
+    pub match_case: bool,
+    /// Plain terms match anywhere in the full path.
+    pub match_path: bool,
+    /// The whole query is one regular expression.
+    pub regex: bool,
+}
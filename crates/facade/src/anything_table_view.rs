@@ -10,14 +10,15 @@ use gpui_component::{
     table::{Table, TableEvent},
     v_flex,
 };
+use protocol::{SearchRequest, SearchResponse};
 use smol::channel::{Receiver, Sender};
 
-use tracing::{debug, trace};
-use vaultify::VAULTIFY;
+use tracing::{debug, trace, warn};
 
-use crate::component::{
-    anything_item::Something,
-    anything_table::{AnythingTableDelegate, OpenSystemFile, OpenSystemFolder, string_to_bool},
+use crate::{
+    Daemon,
+    component::anything_table::{AnythingTableDelegate, OpenSystemFile, OpenSystemFolder},
+    title_bar::ResultCount,
 };
 
 pub struct TableView {
@@ -26,15 +27,14 @@ pub struct TableView {
     // stripe: bool,
     // refresh_data: bool,
     // size: Size,
-    request_sender: Sender<String>,
 }
 
 impl TableView {
     pub fn create(
         window: &mut Window,
         cx: &mut App,
-        request_sender: Sender<String>,
-        data_reciver: Receiver<Vec<Something>>,
+        request_sender: Sender<SearchRequest>,
+        data_reciver: Receiver<SearchResponse>,
     ) -> Entity<Self> {
         cx.new(|cx| Self::new(window, cx, request_sender, data_reciver))
     }
@@ -42,13 +42,13 @@ impl TableView {
     fn new(
         window: &mut Window,
         cx: &mut Context<Self>,
-        request_sender: Sender<String>,
-        data_reciver: Receiver<Vec<Something>>,
+        request_sender: Sender<SearchRequest>,
+        data_reciver: Receiver<SearchResponse>,
     ) -> Self {
         debug!("creating table view");
         let query_input = cx.new(|cx| InputState::new(window, cx).placeholder("file name..."));
 
-        let delegate = AnythingTableDelegate::new();
+        let delegate = AnythingTableDelegate::new(request_sender);
         let table = cx.new(|cx| Table::new(delegate, window, cx));
 
         cx.subscribe_in(&table, window, Self::on_table_event)
@@ -57,35 +57,45 @@ impl TableView {
             .detach();
 
         cx.spawn(async move |this, cx| {
-            while let Ok(data) = data_reciver.recv().await {
-                trace!("Background task received data: {:?}", data.len());
+            while let Ok(response) = data_reciver.recv().await {
+                trace!(
+                    "Background task received data: {} for request {}",
+                    response.hits.len(),
+                    response.id
+                );
                 this.update(cx, |this, cx| {
-                    this.table
-                        .update(cx, |table: &mut Table<AnythingTableDelegate>, _| {
-                            table.delegate_mut().replace_anything(data);
-                        });
-                    cx.notify();
+                    let applied =
+                        this.table
+                            .update(cx, |table: &mut Table<AnythingTableDelegate>, _| {
+                                table.delegate_mut().apply_response(response)
+                            });
+                    if applied {
+                        this.update_result_count(cx);
+                        cx.notify();
+                    }
                 })
                 .ok();
             }
         })
         .detach();
 
+        let client = cx.global::<Daemon>().0.clone();
         cx.spawn(async move |this, cx| {
             loop {
+                let daemon = client.clone();
+                let status = cx
+                    .background_executor()
+                    .spawn(async move { daemon.status() })
+                    .await;
                 let indexed = this
                     .update(cx, |this, cx| {
                         this.table
                             .update(cx, |table: &mut Table<AnythingTableDelegate>, _| {
-                                trace!(
-                                    "the value of indexed accessed by ui: {}",
-                                    table.delegate().indexed
-                                );
-                                if !table.delegate().indexed {
-                                    table.delegate_mut().indexed =
-                                        string_to_bool(VAULTIFY.get("indexed").unwrap()).unwrap();
-                                    trace!("indexed status: {}", table.delegate().indexed);
+                                match status {
+                                    Ok(status) => table.delegate_mut().indexed = status.indexed,
+                                    Err(e) => warn!("failed to read index status: {}", e),
                                 }
+                                trace!("indexed status: {}", table.delegate().indexed);
                                 table.delegate().indexed
                             })
                     })
@@ -107,7 +117,6 @@ impl TableView {
             // stripe: false,
             // refresh_data: false,
             // size: Size::default(),
-            request_sender,
         }
     }
 
@@ -131,6 +140,7 @@ impl TableView {
                     .path
                     .clone();
                 cx.open_with_system(&PathBuf::from(path.to_string()));
+                record_run(cx, &path.to_string());
             }
             _ => {}
         }
@@ -151,12 +161,17 @@ impl TableView {
                     debug!("empty query");
                     self.table
                         .update(cx, |table: &mut Table<AnythingTableDelegate>, _| {
-                            table.delegate_mut().replace_anything(vec![]);
+                            table.delegate_mut().clear();
                         });
+                    self.update_result_count(cx);
                     cx.notify();
                     return;
                 }
-                self.request_sender.try_send(text.clone()).ok();
+                self.table
+                    .update(cx, |table: &mut Table<AnythingTableDelegate>, _| {
+                        table.delegate_mut().query = text.clone();
+                        table.delegate_mut().request(0);
+                    });
                 debug!("request sent: {}", text);
                 cx.notify();
             }
@@ -164,6 +179,15 @@ impl TableView {
         }
     }
 
+    fn update_result_count(&mut self, cx: &mut Context<Self>) {
+        let delegate = self.table.read(cx).delegate();
+        let count = ResultCount {
+            shown: delegate.anything.len(),
+            total: delegate.total,
+        };
+        cx.set_global(count);
+    }
+
     fn on_key_space(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
         if event.keystroke.key == "space" {
             if let Some(selected_row_ix) = self.table.read(cx).selected_row() {
@@ -221,9 +245,22 @@ impl TableView {
             .path
             .clone();
         cx.open_with_system(&PathBuf::from(path.to_string()));
+        record_run(cx, &path.to_string());
     }
 }
 
+fn record_run(cx: &App, path: &str) {
+    let client = cx.global::<Daemon>().0.clone();
+    let path = path.to_string();
+    cx.background_executor()
+        .spawn(async move {
+            if let Err(e) = client.record_run(&path) {
+                warn!("failed to record run of {}: {}", path, e);
+            }
+        })
+        .detach();
+}
+
 impl Render for TableView {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl gpui::IntoElement {
         self.table.update(cx, |table, cx| {
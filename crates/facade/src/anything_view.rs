@@ -1,9 +1,10 @@
 use gpui::{
     App, AppContext, Context, Entity, IntoElement, ParentElement, Render, Styled, Window, div,
 };
+use protocol::{SearchRequest, SearchResponse};
 use smol::channel::{Receiver, Sender};
 
-use crate::{anything_table_view::TableView, component::anything_item::Something};
+use crate::anything_table_view::TableView;
 
 pub struct AnythingView {
     root: Entity<TableView>,
@@ -13,8 +14,8 @@ impl AnythingView {
     pub fn create(
         window: &mut Window,
         cx: &mut App,
-        request_sender: Sender<String>,
-        data_reciver: Receiver<Vec<Something>>,
+        request_sender: Sender<SearchRequest>,
+        data_reciver: Receiver<SearchResponse>,
     ) -> Entity<AnythingView> {
         cx.new(|cx| {
             let root = TableView::create(window, cx, request_sender, data_reciver);
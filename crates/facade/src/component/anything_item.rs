@@ -1,6 +1,8 @@
+use anything_core::SearchHit;
 use gpui::SharedString;
 
 use gpui_component::table::ColSort;
+use time::OffsetDateTime;
 
 #[derive(Debug)]
 pub struct Something {
@@ -9,6 +11,29 @@ pub struct Something {
     pub last_modified_date: time::Date,
     pub name: SharedString,
     pub size: f64,
+    pub run_count: u64,
+    /// When it was last opened from the app, if ever.
+    pub last_run_date: Option<time::Date>,
+}
+
+impl From<SearchHit> for Something {
+    fn from(hit: SearchHit) -> Self {
+        let last_modified_date = OffsetDateTime::from_unix_timestamp(hit.modified)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .date();
+        Something {
+            class: hit.class.into(),
+            path: hit.path.to_string_lossy().into_owned().into(),
+            last_modified_date,
+            name: hit.name.into(),
+            size: hit.size as f64,
+            run_count: hit.run_count,
+            last_run_date: Some(hit.date_run)
+                .filter(|date_run| *date_run > 0)
+                .and_then(|date_run| OffsetDateTime::from_unix_timestamp(date_run).ok())
+                .map(|date_run| date_run.date()),
+        }
+    }
 }
 
 pub struct Column {
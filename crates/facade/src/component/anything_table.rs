@@ -9,7 +9,9 @@ use gpui_component::{
 };
 use material_icon_embed_rs::material_icon_file::MaterialIconFile;
 use material_icon_embed_rs::material_icon_folder::MaterialIconFolder;
-use vaultify::VAULTIFY;
+use protocol::{DEFAULT_LIMIT, SearchRequest, SearchResponse, Sort, SortKey};
+use smol::channel::Sender;
+use tracing::warn;
 
 use super::{
     anything_item::{Column, Something},
@@ -20,6 +22,13 @@ actions!(anything_table_action, [OpenSystemFolder, OpenSystemFile]);
 
 pub struct AnythingTableDelegate {
     pub anything: Vec<Something>,
+    pub query: String,
+    pub sort: Sort,
+    pub total: usize,
+    request_sender: Sender<SearchRequest>,
+    next_id: u64,
+    /// Id and offset of the request whose response the table is waiting for.
+    pending: Option<(u64, usize)>,
     columns: Vec<Column>,
     pub col_order: bool,
     pub col_sort: bool,
@@ -28,32 +37,93 @@ pub struct AnythingTableDelegate {
 }
 
 impl AnythingTableDelegate {
-    pub fn new() -> Self {
+    pub fn new(request_sender: Sender<SearchRequest>) -> Self {
         Self {
             anything: vec![],
+            query: String::new(),
+            sort: Sort::default(),
+            total: 0,
+            request_sender,
+            next_id: 0,
+            pending: None,
             columns: vec![
-                Column::new("class", "Kind", None),
-                Column::new("name", "Name", None),
-                Column::new("path", "Path", None),
+                Column::new("class", "Kind", Some(ColSort::Default)),
+                Column::new("name", "Name", Some(ColSort::Default)),
+                Column::new("path", "Path", Some(ColSort::Default)),
                 Column::new("size", "Size", Some(ColSort::Default)),
                 Column::new(
                     "last_modified_date",
                     "Last Modified",
                     Some(ColSort::Default),
                 ),
+                Column::new("run_count", "Runs", Some(ColSort::Default)),
+                Column::new("last_run_date", "Last Run", Some(ColSort::Default)),
             ],
             col_order: true,
             col_sort: true,
             loading: false,
-            indexed: string_to_bool(VAULTIFY.get("indexed").unwrap()).unwrap(),
+            indexed: false,
         }
     }
 
     pub fn replace_anything(&mut self, new_data: Vec<Something>) {
+        self.total = new_data.len();
         self.anything = new_data;
         self.loading = false;
     }
 
+    /// Clears the rows and forgets any request still in flight.
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.pending = None;
+        self.replace_anything(vec![]);
+    }
+
+    /// Requests the hits of the current query from `offset` on. Any earlier
+    /// request is superseded.
+    pub fn request(&mut self, offset: usize) {
+        let request = SearchRequest {
+            id: self.next_id,
+            query: self.query.clone(),
+            sort: self.sort,
+            offset,
+            limit: DEFAULT_LIMIT,
+            ..Default::default()
+        };
+        match self.request_sender.try_send(request) {
+            Ok(_) => {
+                self.pending = Some((self.next_id, offset));
+                self.next_id += 1;
+                self.loading = true;
+            }
+            Err(e) => warn!("failed to send search request: {}", e),
+        }
+    }
+
+    /// Applies the response to the latest request, replacing the rows for a
+    /// first page and appending later ones. Returns false for responses to
+    /// superseded requests, which are dropped.
+    pub fn apply_response(&mut self, response: SearchResponse) -> bool {
+        match self.pending {
+            Some((id, offset)) if id == response.id => {
+                if let Some(error) = &response.error {
+                    warn!("search for {} failed: {}", self.query, error);
+                }
+                let rows = response.hits.into_iter().map(Something::from);
+                if offset == 0 {
+                    self.anything = rows.collect();
+                } else {
+                    self.anything.extend(rows);
+                }
+                self.total = response.total;
+                self.pending = None;
+                self.loading = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn render_kind_cell(&self, kind: &SharedString, name: &SharedString) -> AnyElement {
         if kind == "folder" {
             return div()
@@ -117,6 +187,8 @@ impl TableDelegate for AnythingTableDelegate {
             2 => 600.0.into(),
             3 => 80.0.into(),
             4 => 120.0.into(),
+            5 => 60.0.into(),
+            6 => 120.0.into(),
             _ => 100.0.into(),
         }
     }
@@ -134,7 +206,11 @@ impl TableDelegate for AnythingTableDelegate {
     }
 
     fn can_load_more(&self, _cx: &App) -> bool {
-        false
+        !self.loading && self.anything.len() < self.total
+    }
+
+    fn load_more(&mut self, _: &mut Window, _: &mut Context<Table<Self>>) {
+        self.request(self.anything.len());
     }
 
     fn context_menu(
@@ -178,6 +254,11 @@ impl TableDelegate for AnythingTableDelegate {
             "path" => something.path.clone().into_any_element(),
             "size" => self.render_value_cell(something.size),
             "last_modified_date" => something.last_modified_date.to_string().into_any_element(),
+            "run_count" => something.run_count.to_string().into_any_element(),
+            "last_run_date" => something
+                .last_run_date
+                .map_or("--".to_string(), |date| date.to_string())
+                .into_any_element(),
             _ => "--".to_string().into_any_element(),
         }
     }
@@ -216,32 +297,29 @@ impl TableDelegate for AnythingTableDelegate {
             return;
         }
 
-        if let Some(col) = self.columns.get_mut(col_ix) {
-            match col.id.as_ref() {
-                "size" => self.anything.sort_by(|a, b| match sort {
-                    ColSort::Descending => b
-                        .size
-                        .partial_cmp(&a.size)
-                        .unwrap_or(std::cmp::Ordering::Equal),
-                    _ => a
-                        .size
-                        .partial_cmp(&b.size)
-                        .unwrap_or(std::cmp::Ordering::Equal),
-                }),
-                "last_modified_date" => self.anything.sort_by(|a, b| match sort {
-                    ColSort::Descending => b.last_modified_date.cmp(&a.last_modified_date),
-                    _ => a.last_modified_date.cmp(&b.last_modified_date),
-                }),
-                _ => {}
-            }
+        let Some(col) = self.columns.get(col_ix) else {
+            return;
+        };
+        let key = match (sort, col.id.as_ref()) {
+            (ColSort::Default, _) => SortKey::Relevance,
+            (_, "class") => SortKey::Kind,
+            (_, "name") => SortKey::Name,
+            (_, "path") => SortKey::Path,
+            (_, "size") => SortKey::Size,
+            (_, "last_modified_date") => SortKey::Modified,
+            (_, "run_count") => SortKey::RunCount,
+            (_, "last_run_date") => SortKey::DateRun,
+            _ => return,
+        };
+        self.sort = Sort {
+            key,
+            descending: matches!(sort, ColSort::Descending),
+        };
+        if self.query.is_empty() {
+            return;
         }
-    }
-}
 
-pub fn string_to_bool(s: String) -> Option<bool> {
-    match s.as_str() {
-        "true" => Some(true),
-        "false" => Some(false),
-        _ => None,
+        // the index sorts the full hit set, so start over from the first page
+        self.request(0);
     }
 }
@@ -1,14 +1,14 @@
 use anything_view::AnythingView;
 use asset::VanillaAsset;
-use component::anything_item::Something;
 use gpui::{
-    App, AppContext, Application, Bounds, KeyBinding, Menu, MenuItem, Window, WindowBounds,
+    App, AppContext, Application, Bounds, Global, KeyBinding, Menu, MenuItem, Window, WindowBounds,
     WindowKind, WindowOptions, actions, px, size,
 };
 use gpui_component::{
     Root, TitleBar,
     input::{Copy, Cut, Paste, Redo, Undo},
 };
+use protocol::{Client, SearchRequest, SearchResponse};
 use root::FacadeRoot;
 use smol::channel::{Receiver, Sender};
 use tracing::{Level, debug, span};
@@ -17,17 +17,29 @@ mod anything_table_view;
 mod anything_view;
 mod asset;
 pub mod component;
+mod mount_settings;
 mod root;
 mod title_bar;
 
 actions!(facade, [Quit, Hide]);
 
-pub fn setup(request_sender: Sender<String>, data_reciver: Receiver<Vec<Something>>) {
+/// The daemon that owns the index, asked for its status and told about opened
+/// files.
+pub struct Daemon(pub Client);
+
+impl Global for Daemon {}
+
+pub fn setup(
+    client: Client,
+    request_sender: Sender<SearchRequest>,
+    data_reciver: Receiver<SearchResponse>,
+) {
     let span = span!(Level::DEBUG, "ui service thread");
     let _enter: span::Entered<'_> = span.enter();
     let app = Application::new().with_assets(VanillaAsset);
     app.run(|cx: &mut App| {
         gpui_component::init(cx);
+        cx.set_global(Daemon(client));
         Facade::shortcut_binding_init(cx);
         Facade::menu_init(cx);
         cx.activate(true);
@@ -43,8 +55,8 @@ impl Facade {
     fn windows_async_init(
         cx: &mut App,
         windows_options: WindowOptions,
-        request_sender: Sender<String>,
-        data_reciver: Receiver<Vec<Something>>,
+        request_sender: Sender<SearchRequest>,
+        data_reciver: Receiver<SearchResponse>,
     ) {
         cx.spawn(async move |cx| {
             let window = cx
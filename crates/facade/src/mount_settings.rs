@@ -0,0 +1,144 @@
+use gpui::{
+    Context, IntoElement, ParentElement, Render, SharedString, Styled, Window, div,
+    prelude::FluentBuilder,
+};
+use gpui_component::{
+    ActiveTheme, Sizable,
+    button::{Button, ButtonVariants},
+    divider::Divider,
+    h_flex, v_flex,
+};
+use protocol::{Mount, MountKind};
+use tracing::warn;
+
+use crate::Daemon;
+
+/// The mounted filesystems with the policy indexing applies to each, which a
+/// click on the policy cycles.
+pub struct MountSettings {
+    mounts: Vec<Mount>,
+    error: Option<SharedString>,
+    loading: bool,
+}
+
+impl MountSettings {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let client = cx.global::<Daemon>().0.clone();
+        cx.spawn(async move |this, cx| {
+            let mounts = cx
+                .background_executor()
+                .spawn(async move { client.mounts() })
+                .await;
+            this.update(cx, |this, cx| {
+                match mounts {
+                    Ok(mounts) => {
+                        this.mounts = mounts
+                            .into_iter()
+                            .filter(|mount| mount.kind != MountKind::Virtual)
+                            .collect();
+                    }
+                    Err(e) => {
+                        warn!("failed to list mounts: {}", e);
+                        this.error = Some(format!("Failed to list mounts: {}", e).into());
+                    }
+                }
+                this.loading = false;
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+        Self {
+            mounts: vec![],
+            error: None,
+            loading: true,
+        }
+    }
+
+    fn toggle(&mut self, ix: usize, cx: &mut Context<Self>) {
+        let Some(mount) = self.mounts.get(ix) else {
+            return;
+        };
+        let mount_point = mount.mount_point.clone();
+        let policy = mount.policy.next();
+        let client = cx.global::<Daemon>().0.clone();
+        cx.spawn(async move |this, cx| {
+            let stored = mount_point.clone();
+            let result = cx
+                .background_executor()
+                .spawn(async move { client.set_mount_policy(&stored, policy) })
+                .await;
+            this.update(cx, |this, cx| {
+                match result {
+                    Ok(()) => {
+                        if let Some(mount) = this
+                            .mounts
+                            .iter_mut()
+                            .find(|mount| mount.mount_point == mount_point)
+                        {
+                            mount.policy = policy;
+                        }
+                    }
+                    Err(e) => warn!("failed to set policy of {}: {}", mount_point.display(), e),
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+}
+
+impl Render for MountSettings {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let muted = cx.theme().muted_foreground;
+        v_flex()
+            .gap_2()
+            .w_96()
+            .child(div().text_sm().child("Mounts"))
+            .child(Divider::horizontal())
+            .when(self.mounts.is_empty(), |this| {
+                this.child(div().text_sm().text_color(muted).child(
+                    match (&self.error, self.loading) {
+                        (Some(error), _) => error.clone(),
+                        (None, true) => "Loading mounts…".into(),
+                        (None, false) => "No mounts found".into(),
+                    },
+                ))
+            })
+            .children(self.mounts.iter().enumerate().map(|(ix, mount)| {
+                h_flex()
+                    .justify_between()
+                    .gap_2()
+                    .child(
+                        v_flex()
+                            .overflow_hidden()
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .child(mount.mount_point.to_string_lossy().to_string()),
+                            )
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(muted)
+                                    .child(format!("{} · {}", mount.fs_type, mount.kind)),
+                            ),
+                    )
+                    .child(
+                        Button::new(("mount-policy", ix))
+                            .label(mount.policy.to_string())
+                            .small()
+                            .ghost()
+                            .on_click(cx.listener(move |this, _, _, cx| this.toggle(ix, cx))),
+                    )
+            }))
+            .child(Divider::horizontal())
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(muted)
+                    .child("Changes apply when the index is next refreshed."),
+            )
+    }
+}
@@ -1,9 +1,9 @@
-use std::{env, f32, path::PathBuf};
+use std::f32;
 
 use gpui::{
-    AppContext, ClickEvent, Context, Corner, Element, Entity, Hsla, InteractiveElement,
-    IntoElement, MouseButton, ParentElement, Pixels, Render, SharedString, Styled, Subscription,
-    Timer, Window, div, prelude::FluentBuilder, px,
+    App, AppContext, ClickEvent, Context, Corner, Element, Entity, Global, Hsla,
+    InteractiveElement, IntoElement, MouseButton, ParentElement, Pixels, Render, SharedString,
+    Styled, Subscription, Timer, Window, div, prelude::FluentBuilder, px,
 };
 use gpui_component::{
     ActiveTheme, ColorName, Icon, IconName, Sizable, Size, Theme, ThemeMode, TitleBar,
@@ -15,8 +15,19 @@ use gpui_component::{
     tag::Tag,
     v_flex,
 };
-use tracing::{debug, trace};
-use vaultify::VAULTIFY;
+use tracing::{debug, trace, warn};
+
+use crate::Daemon;
+use crate::mount_settings::MountSettings;
+
+/// Rows loaded in the result table out of all hits for the current query.
+#[derive(Default)]
+pub struct ResultCount {
+    pub shown: usize,
+    pub total: usize,
+}
+
+impl Global for ResultCount {}
 
 pub struct FacadeTitleBar {
     theme_color: Entity<ColorPickerState>,
@@ -29,29 +40,39 @@ impl FacadeTitleBar {
     pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
         let theme_color =
             cx.new(|cx| ColorPickerState::new(window, cx).default_value(cx.theme().primary));
-        let _subscriptions = vec![cx.subscribe_in(
-            &theme_color,
-            window,
-            |this, _, ev: &ColorPickerEvent, window, cx| match ev {
-                ColorPickerEvent::Change(color) => {
-                    this.set_theme_color(*color, window, cx);
-                }
-            },
-        )];
+        let _subscriptions = vec![
+            cx.subscribe_in(
+                &theme_color,
+                window,
+                |this, _, ev: &ColorPickerEvent, window, cx| match ev {
+                    ColorPickerEvent::Change(color) => {
+                        this.set_theme_color(*color, window, cx);
+                    }
+                },
+            ),
+            cx.observe_global::<ResultCount>(|_, cx| cx.notify()),
+        ];
 
+        let client = cx.global::<Daemon>().0.clone();
         cx.spawn(async move |this, cx| {
             loop {
+                let daemon = client.clone();
+                let status = cx
+                    .background_executor()
+                    .spawn(async move { daemon.status() })
+                    .await;
                 this.update(cx, |this, cx| {
                     trace!(
                         "the value of indexed files accessed by ui: {}",
                         this.index_files_count
                     );
-                    this.index_files_count = VAULTIFY.get("indexed_files").unwrap().into();
-                    this.progress_value = VAULTIFY
-                        .get("indexed_progress")
-                        .unwrap()
-                        .parse::<f32>()
-                        .unwrap();
+                    match status {
+                        Ok(status) => {
+                            this.index_files_count = status.indexed_files.to_string().into();
+                            this.progress_value = status.indexed_progress;
+                        }
+                        Err(e) => warn!("failed to read index status: {}", e),
+                    }
                     trace!("indexed files: {}", this.index_files_count);
                     cx.notify();
                 })
@@ -98,8 +119,25 @@ impl FacadeTitleBar {
     }
 }
 
+/// Asks the daemon to bring the index up to date. It answers once it is done,
+/// while the title bar shows the progress.
+fn refresh_index(cx: &App) {
+    let client = cx.global::<Daemon>().0.clone();
+    cx.background_executor()
+        .spawn(async move {
+            if let Err(e) = client.refresh() {
+                warn!("failed to refresh index: {}", e);
+            }
+        })
+        .detach();
+}
+
 impl Render for FacadeTitleBar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let result_count = cx.try_global::<ResultCount>().and_then(|count| {
+            (count.total > 0).then(|| format!("{} of {} results", count.shown, count.total))
+        });
+
         TitleBar::new().child(div()).child(
             div()
                 .flex()
@@ -108,6 +146,7 @@ impl Render for FacadeTitleBar {
                 .px_2()
                 .gap_2()
                 .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                .children(result_count.map(|count| div().text_sm().mr_4().child(count)))
                 .child(
                     div()
                         .flex()
@@ -149,6 +188,24 @@ impl Render for FacadeTitleBar {
                         .ghost()
                         .on_click(cx.listener(Self::change_theme_mode)),
                 )
+                .child(
+                    Popover::new("mounts_popover")
+                        .anchor(Corner::TopRight)
+                        .trigger(
+                            Button::new("mounts")
+                                .icon(IconName::Settings)
+                                .small()
+                                .ghost(),
+                        )
+                        .content(|window, cx| {
+                            let settings = cx.new(MountSettings::new);
+                            cx.new(|cx| {
+                                PopoverContent::new(window, cx, move |_, _| {
+                                    settings.clone().into_any_element()
+                                })
+                            })
+                        }),
+                )
                 .child(
                     Popover::new("refresh_popover")
                         .anchor(Corner::TopRight)
@@ -171,19 +228,7 @@ impl Render for FacadeTitleBar {
                                                 Button::new("refresh_info")
                                                     .label("Yes")
                                                     .w(px(80.))
-                                                    .on_click(|_, _, cx| {
-                                                        VAULTIFY
-                                                            .set("refresh", "true".to_string())
-                                                            .unwrap();
-                                                        if let Ok(current_exe) = env::current_exe()
-                                                        {
-                                                            cx.restart(Some(current_exe));
-                                                        } else {
-                                                            cx.restart(Some(PathBuf::from(
-                                                                "/Applications/Anything.app",
-                                                            )));
-                                                        }
-                                                    })
+                                                    .on_click(|_, _, cx| refresh_index(cx))
                                                     .small(),
                                             ),
                                         )
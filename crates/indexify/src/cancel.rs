@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tantivy::query::{EmptyScorer, EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TERMINATED, Term};
+
+/// Hands out a token per search; starting a new search cancels every earlier
+/// token.
+#[derive(Clone, Debug, Default)]
+pub struct SearchGeneration(Arc<AtomicU64>);
+
+impl SearchGeneration {
+    pub fn advance(&self) -> CancelToken {
+        let generation = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+        CancelToken {
+            current: Some(self.0.clone()),
+            generation,
+        }
+    }
+}
+
+/// Tells a running search whether a newer one has superseded it. The default
+/// token is never cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    current: Option<Arc<AtomicU64>>,
+    generation: u64,
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|current| current.load(Ordering::Relaxed) != self.generation)
+    }
+}
+
+/// Stops matching documents once the token is cancelled, so collecting a huge
+/// hit set ends early.
+#[derive(Debug)]
+pub struct CancellableQuery {
+    inner: Box<dyn Query>,
+    cancel: CancelToken,
+}
+
+impl CancellableQuery {
+    pub fn new(inner: Box<dyn Query>, cancel: CancelToken) -> Self {
+        CancellableQuery { inner, cancel }
+    }
+}
+
+impl Clone for CancellableQuery {
+    fn clone(&self) -> Self {
+        CancellableQuery::new(self.inner.box_clone(), self.cancel.clone())
+    }
+}
+
+impl Query for CancellableQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(CancellableWeight {
+            inner: self.inner.weight(enable_scoring)?,
+            cancel: self.cancel.clone(),
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.inner.query_terms(visitor);
+    }
+}
+
+struct CancellableWeight {
+    inner: Box<dyn Weight>,
+    cancel: CancelToken,
+}
+
+impl Weight for CancellableWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        if self.cancel.is_cancelled() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        Ok(Box::new(CancellableScorer {
+            inner: self.inner.scorer(reader, boost)?,
+            cancel: self.cancel.clone(),
+            cancelled: false,
+        }))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        self.inner.explain(reader, doc)
+    }
+}
+
+struct CancellableScorer {
+    inner: Box<dyn Scorer>,
+    cancel: CancelToken,
+    cancelled: bool,
+}
+
+impl DocSet for CancellableScorer {
+    fn advance(&mut self) -> DocId {
+        if self.cancel.is_cancelled() {
+            self.cancelled = true;
+            return TERMINATED;
+        }
+        self.inner.advance()
+    }
+
+    fn doc(&self) -> DocId {
+        if self.cancelled {
+            return TERMINATED;
+        }
+        self.inner.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.inner.size_hint()
+    }
+}
+
+impl Scorer for CancellableScorer {
+    fn score(&mut self) -> Score {
+        self.inner.score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_cancels_earlier_tokens() {
+        let generation = SearchGeneration::default();
+        let first = generation.advance();
+        assert!(!first.is_cancelled());
+
+        let second = generation.advance();
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+        assert!(!CancelToken::default().is_cancelled());
+    }
+}
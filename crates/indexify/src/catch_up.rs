@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Instant, UNIX_EPOCH};
+
+use anyhow::Result;
+use tracing::{info, warn};
+use vaultify::VAULTIFY;
+
+use crate::utils::TANTIVY_INDEX;
+use crate::{Stop, get_num_docs, index_add, index_files_until};
+
+/// Applies what was created, renamed or deleted since `since`, in Unix
+/// seconds. Adding or removing an entry touches its folder, so the walk starts
+/// at `roots` and only descends into indexed folders modified since then;
+/// new folders are walked in full. Changes below a folder that was not itself
+/// touched wait for the next reconcile. Paths under `exclude_path` are left
+/// alone.
+pub fn catch_up(since: i64, roots: &[&str], exclude_path: &Vec<String>) -> Result<()> {
+    let start = Instant::now();
+    let indexed = TANTIVY_INDEX.indexed_paths()?;
+    let excluded = |path: &str| exclude_path.iter().any(|exclude| path.starts_with(exclude));
+
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for path in indexed.keys() {
+        if let Some(parent) = Path::new(path).parent().and_then(|parent| parent.to_str()) {
+            children.entry(parent).or_default().push(path);
+        }
+    }
+
+    // roots are no documents of their own, but hold the walked folders
+    let mut pending: Vec<(String, bool)> = roots
+        .iter()
+        .filter(|root| !excluded(root))
+        .map(|root| (root.to_string(), true))
+        .collect();
+    let (mut changed, mut added, mut removed) = (0, 0, 0);
+    while let Some((dir, is_root)) = pending.pop() {
+        let on_disk: HashSet<String> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.path().to_str().map(str::to_string))
+                .collect(),
+            Err(e) => {
+                warn!("failed to list {}: {}", dir, e);
+                continue;
+            }
+        };
+        changed += 1;
+
+        for child in children.get(dir.as_str()).into_iter().flatten() {
+            if !on_disk.contains(*child) {
+                removed += delete_tree(child, &children)?;
+            }
+        }
+
+        for path in &on_disk {
+            if excluded(path) {
+                continue;
+            }
+            match indexed.get(path) {
+                None if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()) => {
+                    // an empty map makes the walk add everything without
+                    // touching the count of the whole index
+                    let mut count = 0;
+                    index_files_until(
+                        path,
+                        exclude_path,
+                        &mut count,
+                        false,
+                        &Stop::default(),
+                        Some(&Mutex::default()),
+                    );
+                    added += count;
+                }
+                None => {
+                    index_add(path)?;
+                    added += 1;
+                }
+                Some(stored) if stored.is_dir => {
+                    if modified_since(path, since) {
+                        pending.push((path.clone(), false));
+                    }
+                }
+                Some(stored) if stored.modified != modified(path).unwrap_or(0) => {
+                    TANTIVY_INDEX.delete(path)?;
+                    index_add(path)?;
+                }
+                Some(_) => {}
+            }
+        }
+
+        // the folder's own document still carries its old modification time
+        if !is_root {
+            TANTIVY_INDEX.delete(&dir)?;
+            index_add(&dir)?;
+        }
+    }
+
+    TANTIVY_INDEX.commit()?;
+    VAULTIFY.set("indexed_files", get_num_docs().to_string())?;
+    info!(
+        "caught up on {} changed folders in {:?}: {} paths added, {} removed",
+        changed,
+        start.elapsed(),
+        added,
+        removed
+    );
+    Ok(())
+}
+
+/// Deletes `path` and everything indexed below it, returning how many
+/// documents that was.
+fn delete_tree(path: &str, children: &HashMap<&str, Vec<&str>>) -> Result<usize> {
+    let mut pending = vec![path];
+    let mut removed = 0;
+    while let Some(path) = pending.pop() {
+        TANTIVY_INDEX.delete(path)?;
+        removed += 1;
+        pending.extend(children.get(path).into_iter().flatten());
+    }
+    Ok(removed)
+}
+
+fn modified(path: &str) -> Option<i64> {
+    let modified = fs::symlink_metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// Whether `dir` still exists and was modified at or after `since`.
+fn modified_since(dir: &str, since: i64) -> bool {
+    modified(dir).is_some_and(|modified| modified >= since)
+}
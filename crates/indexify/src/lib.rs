@@ -1,31 +1,111 @@
+mod cancel;
+mod catch_up;
+mod mounts;
+mod rank;
 mod utils;
 
+use std::collections::HashMap;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, mpsc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{fs, thread};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+use once_cell::sync::Lazy;
 use smol::channel::{Receiver, Sender};
 use tracing::{Level, debug, error, info, span, warn};
-use utils::{TANTIVY_INDEX, get_subfolders};
+use utils::{FileMeta, IndexedPath, READ_ONLY, SCHEMA_VERSION, TANTIVY_INDEX, get_subfolders};
 use vaultify::VAULTIFY;
 
-use facade::component::anything_item::Something;
+use anything_core::{Mount, QueryError, SearchHit};
+use protocol::{SearchRequest, SearchResponse};
+
+pub use cancel::{CancelToken, SearchGeneration};
+pub use catch_up::catch_up;
+pub use mounts::{mounts, set_mount_policy, unwatched_mount_points};
+
+/// How long the walk of one network or removable mount may take before it is
+/// abandoned, keeping what it indexed so far.
+const SLOW_MOUNT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Set while the index catches up on changes made since the app last ran;
+/// the file monitor holds its events until then.
+static CATCHING_UP: AtomicBool = AtomicBool::new(false);
+
+/// Held while the index is built or catches up, so that only one walk of the
+/// disk writes to it at a time.
+static WALKING: Mutex<()> = Mutex::new(());
+
+/// Paths in the index with what is stored about them. A reconcile walk takes
+/// out each path it meets, so the ones left over are gone from disk.
+type Known = Mutex<HashMap<String, IndexedPath>>;
+
+/// Ends a walk early. The walk checks it under the lock before every write, so
+/// once `halt` returns the walk touches neither the index nor `known` again.
+#[derive(Default)]
+struct Stop(Mutex<bool>);
+
+impl Stop {
+    fn halt(&self) {
+        *self.0.lock().unwrap() = true;
+    }
+}
 
 pub fn index_files(path: &str, remain_exclude_path: &Vec<String>, count_total: &mut u64) {
+    index_files_until(
+        path,
+        remain_exclude_path,
+        count_total,
+        false,
+        &Stop::default(),
+        None,
+    );
+}
+
+/// Indexes the files under `path`, leaving out `path` itself when `skip_root`
+/// is set, and stops early once `stop` is halted, leaving what it wrote for the
+/// next commit. With `known` only files that are new or modified since they
+/// were indexed are written.
+fn index_files_until(
+    path: &str,
+    remain_exclude_path: &Vec<String>,
+    count_total: &mut u64,
+    skip_root: bool,
+    stop: &Stop,
+    known: Option<&Known>,
+) {
     let files = utils::get_files(path, remain_exclude_path).unwrap();
     debug!("begin indexing files from {}", path);
     for file in files {
+        if skip_root && file.as_ref().is_ok_and(|file| file.depth == 0) {
+            continue;
+        }
         *count_total += 1;
         match file {
             Ok(file) => {
-                TANTIVY_INDEX
-                    .add(
-                        file.file_name().to_str().expect("Failed to get file name"),
-                        file.path().to_str().expect("Failed to get file path"),
-                    )
-                    .unwrap();
-                if *count_total % 20000 == 0 {
+                let name = file.file_name().to_str().expect("Failed to get file name");
+                let file_path = file.path();
+                let file_path = file_path.to_str().expect("Failed to get file path");
+                let metadata = file.metadata().ok();
+                let meta = FileMeta::new(name, metadata.as_ref());
+                let halted = stop.0.lock().unwrap();
+                if *halted {
+                    warn!("stopped indexing files from {}", path);
+                    return;
+                }
+                match known.map(|known| known.lock().unwrap().remove(file_path)) {
+                    Some(Some(stored)) if stored.modified == meta.modified => {}
+                    // the file monitor may have added a path the index did
+                    // not hold when the walk began
+                    Some(_) => {
+                        TANTIVY_INDEX.delete(file_path).unwrap();
+                        TANTIVY_INDEX.add(name, file_path, &meta).unwrap();
+                    }
+                    None => TANTIVY_INDEX.add(name, file_path, &meta).unwrap(),
+                }
+                // a reconcile leaves the count of the index it updates alone
+                if known.is_none() && *count_total % 20000 == 0 {
                     VAULTIFY
                         .set("indexed_files", count_total.to_string())
                         .unwrap();
@@ -39,67 +119,82 @@ pub fn index_files(path: &str, remain_exclude_path: &Vec<String>, count_total: &
         }
     }
     TANTIVY_INDEX.commit().unwrap();
-    VAULTIFY
-        .set("indexed_files", count_total.to_string())
-        .unwrap();
+    if known.is_none() {
+        VAULTIFY
+            .set("indexed_files", count_total.to_string())
+            .unwrap();
+    }
     debug!("indexed {} files", count_total);
 }
 
-pub fn index_search(query: &str) -> Vec<Something> {
-    TANTIVY_INDEX
-        .search(query)
-        .unwrap()
-        .into_iter()
-        .map(|mut item| {
-            let path_str = item.path.clone().to_string();
-
-            let name = path_str
-                .rsplit('/')
-                .find(|part| !part.is_empty())
-                .unwrap_or("")
-                .to_string();
-
-            if let Ok(metadata) = fs::metadata(&path_str) {
-                let class = name
-                    .rsplit('.')
-                    .next()
-                    .filter(|ext| !ext.is_empty() && ext != &name)
-                    .unwrap_or_else(|| if metadata.is_dir() { "folder" } else { "file" })
-                    .to_string();
-
-                let size = metadata.len() as f64;
-
-                let last_modified_date = metadata
-                    .modified()
-                    .ok()
-                    .and_then(|time| {
-                        use time::OffsetDateTime;
-                        let duration = time.duration_since(std::time::UNIX_EPOCH).ok()?;
-                        OffsetDateTime::from_unix_timestamp(duration.as_secs() as i64).ok()
-                    })
-                    .unwrap_or_else(|| time::OffsetDateTime::now_utc())
-                    .date();
-
-                item.size = size;
-                item.last_modified_date = last_modified_date;
-                item.class = class.into();
-            } else {
-                let class = name
-                    .rsplit('.')
-                    .next()
-                    .filter(|ext| !ext.is_empty() && ext != &name)
-                    .unwrap_or("unknown")
-                    .to_string();
-
-                item.size = 0.0;
-                item.last_modified_date = time::OffsetDateTime::now_utc().date();
-                item.class = class.into();
-            }
+/// Brings the index of the files under `path` in line with the disk: new and
+/// modified files are written, those gone from disk removed and the rest left
+/// alone.
+pub fn reconcile_files(path: &str, remain_exclude_path: &Vec<String>) -> Result<()> {
+    let nested = format!("{}/", path.trim_end_matches('/'));
+    let known: Known = Mutex::new(
+        TANTIVY_INDEX
+            .indexed_paths()?
+            .into_iter()
+            .filter(|(indexed, _)| indexed == path || indexed.starts_with(&nested))
+            .collect(),
+    );
+    let mut count_total = 0;
+    index_files_until(
+        path,
+        remain_exclude_path,
+        &mut count_total,
+        false,
+        &Stop::default(),
+        Some(&known),
+    );
+    remove_gone(&known)
+}
 
-            item.name = name.into();
-            item
-        })
-        .collect()
+/// Deletes the paths a reconcile walk did not meet and commits.
+fn remove_gone(known: &Known) -> Result<()> {
+    let gone = std::mem::take(&mut *known.lock().unwrap());
+    for path in gone.keys() {
+        TANTIVY_INDEX.delete(path)?;
+    }
+    TANTIVY_INDEX.commit()?;
+    debug!("removed {} paths gone from disk", gone.len());
+    Ok(())
+}
+
+/// Returns the first page of hits for `query`.
+pub fn index_search(query: &str) -> Result<Vec<SearchHit>, QueryError> {
+    let request = SearchRequest {
+        query: query.to_string(),
+        ..Default::default()
+    };
+    Ok(TANTIVY_INDEX.search(&request, &CancelToken::default())?.0)
+}
+
+/// Answers a search request, reporting an invalid query in the response.
+pub fn index_search_request(request: &SearchRequest) -> SearchResponse {
+    index_search_cancellable(request, &CancelToken::default())
+}
+
+/// Answers a search request unless `cancel` fires first, in which case the
+/// response carries the cancellation as its error.
+pub fn index_search_cancellable(request: &SearchRequest, cancel: &CancelToken) -> SearchResponse {
+    let start = Instant::now();
+    let (hits, total, error) = match TANTIVY_INDEX.search(request, cancel) {
+        Err(QueryError::Cancelled) => (vec![], 0, Some(QueryError::Cancelled.to_string())),
+        Ok((hits, total)) => (hits, total, None),
+        Err(e) => {
+            warn!("invalid search query {}: {}", request.query, e);
+            (vec![], 0, Some(e.to_string()))
+        }
+    };
+    SearchResponse {
+        id: request.id,
+        hits,
+        total,
+        elapsed: start.elapsed(),
+        error,
+    }
 }
 
 pub fn index_delete(path: &str) -> Result<()> {
@@ -108,12 +203,12 @@ pub fn index_delete(path: &str) -> Result<()> {
 }
 
 pub fn index_add(path: &str) -> Result<()> {
-    TANTIVY_INDEX.add(
-        path.rsplit(|c| c == '/')
-            .find(|part: &&str| !part.is_empty())
-            .unwrap(),
-        path,
-    )?;
+    let name = path
+        .rsplit(|c| c == '/')
+        .find(|part: &&str| !part.is_empty())
+        .unwrap();
+    let metadata = fs::symlink_metadata(path).ok();
+    TANTIVY_INDEX.add(name, path, &FileMeta::new(name, metadata.as_ref()))?;
     Ok(())
 }
 
@@ -127,85 +222,310 @@ pub fn index_list() -> Result<()> {
     Ok(())
 }
 
+/// Opens the index built by the app for searching only, failing when it has
+/// not been built yet or was built with another schema.
+pub fn open_index_read_only() -> Result<()> {
+    let indexed = VAULTIFY.get("indexed").unwrap_or_default();
+    let tantivy_path = VAULTIFY.get("tantivy_path").unwrap_or_default();
+    if indexed != "true" || !Path::new(&tantivy_path).join("meta.json").exists() {
+        bail!("no index found, start Anything once to build it");
+    }
+    let schema_version = VAULTIFY.get("schema_version").unwrap_or_default();
+    if schema_version != SCHEMA_VERSION {
+        bail!(
+            "index schema {} is outdated, start Anything to rebuild it",
+            schema_version
+        );
+    }
+    READ_ONLY.store(true, Ordering::SeqCst);
+    debug!("opening index at {} read-only", tantivy_path);
+    Lazy::force(&TANTIVY_INDEX);
+    Ok(())
+}
+
 pub fn init_index() -> Result<()> {
     if get_indexed_status()? {
         info!("index already initialized, skipping");
         return Ok(());
+    }
+    build_index()
+}
+
+/// Walks the filesystem into the index. An index that already has documents
+/// is reconciled: only new, modified and deleted paths are written, so it keeps
+/// answering searches as before while the walk runs.
+fn build_index() -> Result<()> {
+    let _walking = walking();
+    // an interrupted walk is taken up again on the next start
+    VAULTIFY.batch_set(&[("refresh", "true"), ("indexed_progress", "0.0")])?;
+    let start = SystemTime::now();
+    let known: Option<Arc<Known>> = if get_num_docs() > 0 {
+        let indexed_paths = TANTIVY_INDEX.indexed_paths()?;
+        info!("reconciling {} indexed paths", indexed_paths.len());
+        Some(Arc::new(Mutex::new(indexed_paths)))
     } else {
-        let start = SystemTime::now();
-        let mut default_exclude_path = serde_json::from_str::<Vec<String>>(
-            VAULTIFY.get("default_exclude_path").unwrap().as_str(),
-        )?;
-        let root_subfolder = get_subfolders("/");
-        debug!("root_subfolder: {:?}", root_subfolder);
-
-        let (excluded_paths, remaining_paths): (Vec<String>, Vec<String>) = root_subfolder
-            .into_iter()
-            .partition(|path| default_exclude_path.contains(path));
+        None
+    };
+    // network and removable mounts are walked on their own after the rest
+    let mounts = mounts::mounts();
+    let slow_mounts: Vec<String> = mounts
+        .iter()
+        .filter(|mount| mount.policy.is_indexed() && mounts::is_slow(mount.kind))
+        .map(|mount| mount.mount_point.to_string_lossy().to_string())
+        .collect();
+    let mut default_exclude_path = exclude_paths(&mounts, |mount| {
+        !mount.policy.is_indexed() || mounts::is_slow(mount.kind)
+    })?;
+    let root_subfolder = get_subfolders("/");
+    debug!("root_subfolder: {:?}", root_subfolder);
 
-        for path in &excluded_paths {
-            debug!("skipping path: {}", path);
-        }
+    let (excluded_paths, remaining_paths): (Vec<String>, Vec<String>) = root_subfolder
+        .into_iter()
+        .partition(|path| default_exclude_path.contains(path));
 
-        default_exclude_path.retain(|path| !excluded_paths.contains(path));
+    for path in &excluded_paths {
+        debug!("skipping path: {}", path);
+    }
 
-        let mut count_percent = 0.0;
-        let mut count_total: u64 = 0;
-        let total_paths = remaining_paths.len();
-        for (index, path) in remaining_paths.into_iter().enumerate() {
-            debug!("processing path: {}", path);
-            index_files(path.as_str(), &default_exclude_path, &mut count_total);
-            count_percent = ((index + 1) as f64 / total_paths as f64) * 100.0;
-            VAULTIFY.set("indexed_progress", count_percent.to_string())?;
-        }
-        debug!("completed processing all paths: {:.1}%", count_percent);
-
-        let duration = start.elapsed()?;
-        VAULTIFY.set("indexed", "true".to_string())?;
-        VAULTIFY.set(
-            "last_indexed",
-            start
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                .to_string(),
-        )?;
-        VAULTIFY.set("indexed_files", get_num_docs().to_string())?;
-        info!(
-            "index initialized successfully in {} seconds",
-            duration.as_secs()
+    default_exclude_path.retain(|path| !excluded_paths.contains(path));
+
+    let mut count_percent = 0.0;
+    let mut count_total: u64 = 0;
+    let total_paths = remaining_paths.len() + slow_mounts.len();
+    for (index, path) in remaining_paths.iter().enumerate() {
+        debug!("processing path: {}", path);
+        index_files_until(
+            path,
+            &default_exclude_path,
+            &mut count_total,
+            false,
+            &Stop::default(),
+            known.as_deref(),
+        );
+        count_percent = ((index + 1) as f64 / total_paths as f64) * 100.0;
+        VAULTIFY.set("indexed_progress", count_percent.to_string())?;
+    }
+    for (index, mount) in slow_mounts.iter().enumerate() {
+        let exclude_path: Vec<String> = default_exclude_path
+            .iter()
+            .filter(|path| *path != mount)
+            .cloned()
+            .collect();
+        // below the root the walk of its parent indexed the mount point itself
+        let skip_root = Path::new(mount).parent() != Some(Path::new("/"));
+        count_total = index_slow_mount(
+            mount,
+            exclude_path,
+            count_total,
+            skip_root,
+            known.clone(),
+            SLOW_MOUNT_TIMEOUT,
         );
+        count_percent = ((remaining_paths.len() + index + 1) as f64 / total_paths as f64) * 100.0;
+        VAULTIFY.set("indexed_progress", count_percent.to_string())?;
+    }
+    debug!("completed processing all paths: {:.1}%", count_percent);
+
+    if let Some(known) = known {
+        remove_gone(&known)?;
     }
+
+    let duration = start.elapsed()?;
+    VAULTIFY.set("indexed", "true".to_string())?;
+    let started = start.duration_since(UNIX_EPOCH)?.as_secs().to_string();
+    // the walk saw the disk as of its start, which the next catch-up goes from
+    VAULTIFY.batch_set(&[
+        ("last_indexed", started.as_str()),
+        ("last_seen", started.as_str()),
+        ("refresh", "false"),
+    ])?;
+    VAULTIFY.set("indexed_files", get_num_docs().to_string())?;
+    info!(
+        "index initialized successfully in {} seconds",
+        duration.as_secs()
+    );
     Ok(())
 }
 
-pub fn init_service(
-    request_reciver: Receiver<String>,
-    data_sender: Sender<Vec<Something>>,
-) -> Result<()> {
+/// Brings the whole index in line with the disk now and returns once it is,
+/// after any build or catch-up already running. Searches go on meanwhile.
+pub fn refresh_index() -> Result<()> {
+    build_index()
+}
+
+/// Waits for the walk of the disk already running, if any.
+fn walking() -> MutexGuard<'static, ()> {
+    WALKING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Walks a network or removable mount on its own thread, giving up after
+/// `timeout` so a hung server cannot stall the build. Returns the
+/// file count after the walk, or the one before it when the walk timed out.
+/// A reconcile keeps what is indexed of a mount it could not walk in full.
+fn index_slow_mount(
+    mount: &str,
+    exclude_path: Vec<String>,
+    count_total: u64,
+    skip_root: bool,
+    known: Option<Arc<Known>>,
+    timeout: Duration,
+) -> u64 {
+    debug!("processing mount: {}", mount);
+    let stop = Arc::new(Stop::default());
+    let (done_sender, done_reciver) = mpsc::channel();
+    let walk_stop = stop.clone();
+    let walk_mount = mount.to_string();
+    let walk_known = known.clone();
+    thread::spawn(move || {
+        let mut count = count_total;
+        index_files_until(
+            &walk_mount,
+            &exclude_path,
+            &mut count,
+            skip_root,
+            &walk_stop,
+            walk_known.as_deref(),
+        );
+        done_sender.send(count).ok();
+    });
+    match done_reciver.recv_timeout(timeout) {
+        Ok(count) => count,
+        Err(_) => {
+            warn!(
+                "indexing {} took longer than {:?}, leaving it incomplete",
+                mount, timeout
+            );
+            // the walk may still be writing, so it has to stop before the
+            // entries of the mount are taken out of `known`
+            stop.halt();
+            if let Some(known) = known {
+                known
+                    .lock()
+                    .unwrap()
+                    .retain(|path, _| !Path::new(path).starts_with(mount));
+            }
+            count_total
+        }
+    }
+}
+
+/// The configured exclusions plus the mount points `skip` picks out.
+fn exclude_paths(mounts: &[Mount], skip: impl Fn(&Mount) -> bool) -> Result<Vec<String>> {
+    let mut exclude_path =
+        serde_json::from_str::<Vec<String>>(VAULTIFY.get("default_exclude_path")?.as_str())?;
+    for mount in mounts.iter().filter(|mount| skip(mount)) {
+        debug!(
+            "mount {} is skipped or walked on its own: {:?}",
+            mount.mount_point.display(),
+            mount
+        );
+        exclude_path.push(mount.mount_point.to_string_lossy().to_string());
+    }
+    Ok(exclude_path)
+}
+
+/// Checks the index and brings it up to date on a background thread when it
+/// is outdated, or else catches up on what changed while the app was not
+/// running. The index may be searched once this returns, even while the
+/// update is still running.
+pub fn init_service() -> Result<()> {
     info!("Initializing index service...");
-    thread::spawn(move || -> Result<()> {
-        smol::block_on(async move {
+    if get_indexed_status()? {
+        info!("index already initialized, catching up on offline changes");
+        let since = VAULTIFY
+            .get("last_seen")
+            .or_else(|_| VAULTIFY.get("last_indexed"))?
+            .parse::<i64>()?;
+        CATCHING_UP.store(true, Ordering::SeqCst);
+        thread::spawn(move || {
             let span = span!(Level::DEBUG, "index service thread");
             let _enter = span.enter();
-            init_index().expect("Failed to initialize index");
-            while let Ok(data) = request_reciver.recv().await {
-                let results = index_search(data.as_str());
-                debug!("Search results: {:?}", results.len());
-                if let Err(e) = data_sender.try_send(results) {
-                    error!("Failed to send results: {}", e);
-                }
+            let _walking = walking();
+            // mounts that are not followed wait for the next full walk
+            let exclude_path = exclude_paths(&mounts::mounts(), |mount| {
+                !mount.policy.is_watched() || mounts::is_slow(mount.kind)
+            });
+            if let Err(e) = exclude_path.and_then(|path| catch_up(since, &["/"], &path)) {
+                error!("failed to catch up on offline changes: {}", e);
             }
+            CATCHING_UP.store(false, Ordering::SeqCst);
         });
-        Ok(())
+        return Ok(());
+    }
+    thread::spawn(|| {
+        let span = span!(Level::DEBUG, "index service thread");
+        let _enter = span.enter();
+        build_index().expect("Failed to initialize index");
     });
     Ok(())
 }
 
+/// Answers one client's search requests on a worker thread. Requests queued
+/// behind a running search are coalesced so only the newest runs, and a newer
+/// request cancels the running one.
+pub fn serve(request_reciver: Receiver<SearchRequest>, data_sender: Sender<SearchResponse>) {
+    let latest: Arc<Mutex<Option<(SearchRequest, CancelToken)>>> = Arc::default();
+    let (wake_sender, wake_reciver) = smol::channel::bounded::<()>(1);
+
+    let pending = latest.clone();
+    thread::spawn(move || {
+        smol::block_on(async move {
+            let generation = SearchGeneration::default();
+            while let Ok(request) = request_reciver.recv().await {
+                let mut pending = pending.lock().unwrap();
+                if let Some((superseded, _)) = pending.replace((request, generation.advance())) {
+                    debug!("dropping queued request {}", superseded.id);
+                }
+                // a full channel already holds a wake-up for the worker
+                wake_sender.try_send(()).ok();
+            }
+        })
+    });
+
+    thread::spawn(move || {
+        smol::block_on(async move {
+            let span = span!(Level::DEBUG, "search service thread");
+            let _enter = span.enter();
+            while wake_reciver.recv().await.is_ok() {
+                let Some((request, cancel)) = latest.lock().unwrap().take() else {
+                    continue;
+                };
+                let response = index_search_cancellable(&request, &cancel);
+                if cancel.is_cancelled() {
+                    debug!(
+                        "request {} superseded after {:?}",
+                        request.id, response.elapsed
+                    );
+                    continue;
+                }
+                debug!(
+                    "Search results for request {}: {} of {} in {:?}",
+                    response.id,
+                    response.hits.len(),
+                    response.total,
+                    response.elapsed
+                );
+                if let Err(e) = data_sender.try_send(response) {
+                    error!("Failed to send results: {}", e);
+                }
+            }
+        })
+    });
+}
+
+/// Whether changes made while the app was not running are still being
+/// applied. File events have to wait until they are.
+pub fn is_catching_up() -> bool {
+    CATCHING_UP.load(Ordering::SeqCst)
+}
+
 pub fn get_num_docs() -> u64 {
     TANTIVY_INDEX.get_num_docs()
 }
 
+/// Tells whether the index is current. When it is not, an index that cannot
+/// be reused is removed, while an outdated one is left in place for
+/// `build_index` to reconcile.
 pub fn get_indexed_status() -> Result<bool> {
     let current_version = env!("CARGO_PKG_VERSION");
     let stored_version = VAULTIFY.get("version")?;
@@ -228,34 +548,117 @@ pub fn get_indexed_status() -> Result<bool> {
         }
         Ok(())
     };
-
-    if current_time - last_indexed > fifteen_days_in_seconds {
-        reset_index_state()?;
-        return Ok(false);
-    }
+    // the index keeps serving and its count stays right while it is updated;
+    // the refresh flag stays up until the update is done, so an interrupted
+    // one is taken up again on the next start
+    let reconcile_index_state = || -> Result<()> {
+        VAULTIFY.batch_set(&[("refresh", "true"), ("indexed_progress", "0.0")])?;
+        Ok(())
+    };
 
     if current_version != stored_version {
         VAULTIFY.set("version", current_version.to_string())?;
+    }
+
+    let stored_schema_version = VAULTIFY.get("schema_version").unwrap_or_default();
+    if stored_schema_version != SCHEMA_VERSION {
+        VAULTIFY.set("schema_version", SCHEMA_VERSION.to_string())?;
         reset_index_state()?;
         debug!(
-            "reindexing due to version change: {} -> {}",
-            current_version, stored_version
+            "reindexing due to schema change: {} -> {}",
+            stored_schema_version, SCHEMA_VERSION
         );
         return Ok(false);
     }
 
-    if VAULTIFY.get("refresh")? == "true" {
+    if VAULTIFY.get("indexed")? == "false" {
         reset_index_state()?;
-        debug!("reindexing due to refresh flag being true");
+        debug!("reindexing due to indexed flag being false");
         return Ok(false);
     }
 
-    if VAULTIFY.get("indexed")? == "false" {
-        reset_index_state()?;
-        debug!("reindexing due to indexed flag being false");
+    if current_time - last_indexed > fifteen_days_in_seconds {
+        reconcile_index_state()?;
+        debug!("reconciling index last built at {}", last_indexed);
+        return Ok(false);
+    }
+
+    if current_version != stored_version {
+        reconcile_index_state()?;
+        debug!(
+            "reconciling index due to version change: {} -> {}",
+            stored_version, current_version
+        );
+        return Ok(false);
+    }
+
+    if VAULTIFY.get("refresh")? == "true" {
+        reconcile_index_state()?;
+        debug!("reconciling index due to refresh flag being true");
         return Ok(false);
     }
 
     debug!("index is up to date");
     Ok(true)
 }
+
+#[cfg(test)]
+#[cfg(feature = "mock")]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn files(count: usize) -> TempDir {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        for index in 0..count {
+            fs::write(dir.path().join(index.to_string()), "").unwrap();
+        }
+        dir
+    }
+
+    fn known(paths: &[&str]) -> Known {
+        let stored = IndexedPath {
+            modified: 0,
+            is_dir: false,
+        };
+        Mutex::new(
+            paths
+                .iter()
+                .map(|path| (path.to_string(), stored))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_halted_walk() {
+        let dir = files(1);
+        let file = dir.path().join("0");
+        let known = known(&[file.to_str().unwrap()]);
+        let stop = Stop::default();
+        stop.halt();
+        let mut count = 0;
+        index_files_until(
+            dir.path().to_str().unwrap(),
+            &vec![],
+            &mut count,
+            true,
+            &stop,
+            Some(&known),
+        );
+        assert!(known.lock().unwrap().contains_key(file.to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_slow_mount_timeout() {
+        let dir = files(2000);
+        let mount = dir.path().to_str().unwrap();
+        let inside = format!("{}/gone", mount);
+        let known = Arc::new(known(&[&inside, "/elsewhere"]));
+        let count = index_slow_mount(mount, vec![], 7, false, Some(known.clone()), Duration::ZERO);
+        assert_eq!(count, 7);
+        // the mount keeps what is indexed of it, and the walk leaves the rest
+        let remaining: Vec<String> = known.lock().unwrap().keys().cloned().collect();
+        assert_eq!(remaining, vec!["/elsewhere".to_string()]);
+    }
+}
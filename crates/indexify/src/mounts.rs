@@ -0,0 +1,270 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use anything_core::{Mount, MountKind, MountPolicy};
+use tracing::{debug, warn};
+use vaultify::VAULTIFY;
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+const SYS_BLOCK: &str = "/sys/class/block";
+/// Vault key of the policies chosen for mount points, as a JSON object.
+const POLICY_KEY: &str = "mount_policy";
+
+/// Filesystems whose files live in memory or are generated by the kernel, so
+/// indexing them only adds noise.
+const VIRTUAL_FS_TYPES: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "overlay",
+    "proc",
+    "pstore",
+    "ramfs",
+    "securityfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+const NETWORK_FS_TYPES: &[&str] = &[
+    "9p",
+    "afs",
+    "ceph",
+    "cifs",
+    "davfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+    "fuse.sshfs",
+    "glusterfs",
+    "ncpfs",
+    "nfs",
+    "nfs4",
+    "smb3",
+    "smbfs",
+    "sshfs",
+];
+
+/// A line of mountinfo: mount point, filesystem type and source.
+type MountEntry = (PathBuf, String, String);
+
+/// Every mount but the root, with the policy stored for it or else the
+/// default for its kind. Empty where the kernel has no mountinfo.
+pub fn mounts() -> Vec<Mount> {
+    let mountinfo = match fs::read_to_string(MOUNTINFO) {
+        Ok(mountinfo) => mountinfo,
+        Err(e) => {
+            debug!("no mount table to read: {}", e);
+            return vec![];
+        }
+    };
+    let policies = stored_policies();
+    parse_mountinfo(&mountinfo)
+        .into_iter()
+        .map(|(mount_point, fs_type, source)| {
+            let kind = classify(&fs_type, &source);
+            let policy = policies
+                .get(&mount_point)
+                .copied()
+                .unwrap_or(MountPolicy::default_for(kind));
+            Mount {
+                mount_point,
+                fs_type,
+                kind,
+                policy,
+            }
+        })
+        .collect()
+}
+
+/// Stores the policy for `mount_point`. It applies from the next index build.
+pub fn set_mount_policy(mount_point: &Path, policy: MountPolicy) -> Result<()> {
+    let mut policies = stored_policies();
+    policies.insert(mount_point.to_path_buf(), policy);
+    VAULTIFY.set(POLICY_KEY, serde_json::to_string(&policies)?)
+}
+
+/// Mount points whose changes the file monitor ignores.
+pub fn unwatched_mount_points() -> Vec<PathBuf> {
+    mounts()
+        .into_iter()
+        .filter(|mount| !mount.policy.is_watched())
+        .map(|mount| mount.mount_point)
+        .collect()
+}
+
+/// Network and removable mounts, which can stall a walk.
+pub fn is_slow(kind: MountKind) -> bool {
+    matches!(kind, MountKind::Network | MountKind::Removable)
+}
+
+fn stored_policies() -> HashMap<PathBuf, MountPolicy> {
+    let Ok(policies) = VAULTIFY.get(POLICY_KEY) else {
+        return HashMap::new();
+    };
+    serde_json::from_str(&policies).unwrap_or_else(|e| {
+        warn!("ignoring unreadable mount policies: {}", e);
+        HashMap::new()
+    })
+}
+
+fn classify(fs_type: &str, source: &str) -> MountKind {
+    if VIRTUAL_FS_TYPES.contains(&fs_type) {
+        MountKind::Virtual
+    } else if NETWORK_FS_TYPES.contains(&fs_type) || source.starts_with("//") {
+        MountKind::Network
+    } else if is_removable(source) {
+        MountKind::Removable
+    } else {
+        MountKind::Local
+    }
+}
+
+/// Asks sysfs whether the device behind `source`, or the disk holding that
+/// partition, is removable or attached over USB.
+fn is_removable(source: &str) -> bool {
+    let Some(device) = source.strip_prefix("/dev/") else {
+        return false;
+    };
+    let Ok(device) = fs::canonicalize(Path::new(SYS_BLOCK).join(device)) else {
+        return false;
+    };
+    device.to_string_lossy().contains("/usb")
+        || [device.join("removable"), device.join("../removable")]
+            .iter()
+            .any(|flag| fs::read_to_string(flag).is_ok_and(|flag| flag.trim() == "1"))
+}
+
+/// Reads mountinfo lines such as
+/// `22 1 0:21 / /proc rw,nosuid shared:12 - proc proc rw`. The root is left
+/// out as it holds everything to index, even when it is the overlay of a
+/// container. Of mounts stacked on one point only the top one is kept.
+fn parse_mountinfo(mountinfo: &str) -> Vec<MountEntry> {
+    let entries: Vec<MountEntry> = mountinfo
+        .lines()
+        .filter_map(|line| {
+            let (fields, filesystem) = line.split_once(" - ")?;
+            let mount_point = fields.split(' ').nth(4)?;
+            let mut filesystem = filesystem.split(' ');
+            let fs_type = filesystem.next()?;
+            let source = filesystem.next().unwrap_or_default();
+            (mount_point != "/").then(|| {
+                (
+                    PathBuf::from(unescape(mount_point)),
+                    fs_type.to_string(),
+                    unescape(source),
+                )
+            })
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    let mut entries: Vec<MountEntry> = entries
+        .into_iter()
+        .rev()
+        .filter(|(mount_point, _, _)| seen.insert(mount_point.clone()))
+        .collect();
+    entries.reverse();
+    entries
+}
+
+/// Undoes the octal escapes mountinfo uses for spaces, tabs, newlines and
+/// backslashes in paths.
+fn unescape(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let code = tail
+            .get(..3)
+            .filter(|_| byte == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                bytes.push(code);
+                rest = &tail[3..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mountinfo = "\
+30 1 0:26 / / rw,relatime - overlay overlay rw,lowerdir=/l,upperdir=/u
+31 30 0:27 / /proc rw,nosuid,nodev,noexec - proc proc rw
+32 30 0:28 / /sys ro,nosuid - sysfs sysfs ro
+34 30 0:30 / /run rw,nosuid shared:5 - tmpfs tmpfs rw,mode=755
+35 30 8:1 /home /home rw,relatime shared:1 - ext4 /dev/sda1 rw
+36 30 0:31 / /mnt/nas rw - nfs4 nas:/export rw
+37 30 0:32 / /mnt/my\\040share rw - cifs //server/share rw
+38 30 0:33 / /run rw,nosuid - tmpfs tmpfs rw,mode=700
+";
+        let entries = parse_mountinfo(mountinfo);
+        let kinds: Vec<(&str, MountKind)> = entries
+            .iter()
+            .map(|(mount_point, fs_type, source)| {
+                (mount_point.to_str().unwrap(), classify(fs_type, source))
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("/proc", MountKind::Virtual),
+                ("/sys", MountKind::Virtual),
+                ("/home", MountKind::Local),
+                ("/mnt/nas", MountKind::Network),
+                ("/mnt/my share", MountKind::Network),
+                ("/run", MountKind::Virtual),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_mount_policy() {
+        let nas = Path::new("/mnt/nas");
+        let usb = Path::new("/media/usb");
+        set_mount_policy(nas, MountPolicy::Index).unwrap();
+        set_mount_policy(usb, MountPolicy::Skip).unwrap();
+        set_mount_policy(nas, MountPolicy::IndexWithoutWatching).unwrap();
+        let policies = stored_policies();
+        assert_eq!(policies.len(), 2);
+        assert_eq!(policies[nas], MountPolicy::IndexWithoutWatching);
+        assert_eq!(policies[usb], MountPolicy::Skip);
+
+        // a stored policy wins over the default for the kind of mount
+        if let Some(mount) = mounts().into_iter().next() {
+            let policy = mount.policy.next();
+            set_mount_policy(&mount.mount_point, policy).unwrap();
+            let stored = mounts()
+                .into_iter()
+                .find(|other| other.mount_point == mount.mount_point)
+                .unwrap();
+            assert_eq!(stored.policy, policy);
+        }
+
+        VAULTIFY.set(POLICY_KEY, "{".to_string()).unwrap();
+        assert!(stored_policies().is_empty());
+    }
+}
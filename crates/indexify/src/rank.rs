@@ -0,0 +1,134 @@
+use std::path::{Component, Path};
+
+use tantivy::Score;
+use tracing::warn;
+use vaultify::VAULTIFY;
+
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+const SECONDS_PER_DAY: f32 = 86_400.0;
+
+/// Weights of the ranking signals, stored in the vault as `rank_*` keys so
+/// they can be tuned without a rebuild.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RankWeights {
+    pub exact: f32,
+    pub prefix: f32,
+    pub depth: f32,
+    pub home: f32,
+    pub recency: f32,
+    pub runs: f32,
+}
+
+impl Default for RankWeights {
+    fn default() -> Self {
+        RankWeights {
+            exact: 100.0,
+            prefix: 50.0,
+            depth: 0.5,
+            home: 5.0,
+            recency: 5.0,
+            runs: 10.0,
+        }
+    }
+}
+
+impl RankWeights {
+    /// Reads the weights from the vault, seeding any missing key with its
+    /// default.
+    pub fn load() -> Self {
+        let mut weights = RankWeights::default();
+        let mut missing = vec![];
+        for (key, weight) in [
+            ("rank_exact", &mut weights.exact),
+            ("rank_prefix", &mut weights.prefix),
+            ("rank_depth", &mut weights.depth),
+            ("rank_home", &mut weights.home),
+            ("rank_recency", &mut weights.recency),
+            ("rank_runs", &mut weights.runs),
+        ] {
+            match VAULTIFY.get(key).map(|value| value.parse::<f32>()) {
+                Ok(Ok(value)) => *weight = value,
+                Ok(Err(e)) => warn!("ignoring invalid {}: {}", key, e),
+                Err(_) => missing.push((key, weight.to_string())),
+            }
+        }
+
+        if !missing.is_empty() {
+            let pairs: Vec<(&str, &str)> = missing
+                .iter()
+                .map(|(key, value)| (*key, value.as_str()))
+                .collect();
+            if let Err(e) = VAULTIFY.batch_set(&pairs) {
+                warn!("failed to seed rank weights: {}", e);
+            }
+        }
+        weights
+    }
+}
+
+/// Combines the query score with the path and recency signals of a document.
+#[derive(Clone, Copy)]
+pub struct Ranker {
+    weights: RankWeights,
+    home_depth: u64,
+    now: i64,
+}
+
+impl Ranker {
+    pub fn new(weights: RankWeights, home_dir: &str, now: i64) -> Self {
+        Ranker {
+            weights,
+            home_depth: path_depth(home_dir),
+            now,
+        }
+    }
+
+    pub fn score(&self, score: Score, depth: u64, in_home: bool, modified: i64) -> Score {
+        let mut score = score - self.weights.depth * depth as f32;
+        if in_home {
+            let below_home = depth.saturating_sub(self.home_depth) as f32;
+            score += self.weights.home / (1.0 + below_home);
+        }
+        if modified > 0 {
+            let age_days = (self.now - modified).max(0) as f32 / SECONDS_PER_DAY;
+            score += self.weights.recency * 0.5f32.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        }
+        score
+    }
+}
+
+/// Number of named components in the path.
+pub fn path_depth(path: &str) -> u64 {
+    Path::new(path)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_749_600_000;
+
+    #[test]
+    fn test_path_depth() {
+        assert_eq!(path_depth("/"), 0);
+        assert_eq!(path_depth("/home/user/index.js"), 3);
+        assert_eq!(path_depth("/home/user/"), 2);
+    }
+
+    #[test]
+    fn test_shallow_home_file_outranks_nested_one() {
+        let ranker = Ranker::new(RankWeights::default(), "/home/user", NOW);
+        let nested = "/home/user/code/app/node_modules/pkg/lib/index.js";
+        let shallow = "/home/user/index.js";
+
+        assert!(
+            ranker.score(1.0, path_depth(shallow), true, NOW - 400 * 86_400)
+                > ranker.score(1.0, path_depth(nested), true, NOW - 400 * 86_400)
+        );
+        assert!(ranker.score(1.0, 3, true, 0) > ranker.score(1.0, 3, false, 0));
+        assert!(ranker.score(1.0, 3, false, NOW) > ranker.score(1.0, 3, false, NOW - 86_400 * 90));
+    }
+}
@@ -1,36 +1,128 @@
 use anyhow::Result;
-use facade::component::anything_item::Something;
+use anything_core::query::{self, Expr, Modifier};
+use anything_core::{QueryError, SearchHit};
 use jwalk::{WalkDir, WalkDirGeneric};
 use once_cell::sync::Lazy;
+use pinyin::ToPinyin;
+use protocol::{SearchRequest, Sort, SortKey};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fs;
-use std::sync::Mutex;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
 use tantivy::{
-    Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, TantivyError, Term,
-    collector::TopDocs,
+    DocAddress, DocId, Index, IndexReader, IndexWriter, Order, ReloadPolicy, Score, Searcher,
+    SegmentReader, TantivyDocument, TantivyError, Term,
+    collector::{Collector, Count, TopDocs},
     doc,
-    query::QueryParser,
+    query::{
+        AllQuery, BooleanQuery, ConstScoreQuery, EmptyQuery, FuzzyTermQuery, Occur, PhraseQuery,
+        Query, RangeQuery, RegexQuery, TermQuery, TermSetQuery,
+    },
     schema::{
-        Field, INDEXED, IndexRecordOption, STORED, Schema, TextFieldIndexing, TextOptions, Value,
+        FAST, Field, INDEXED, IndexRecordOption, STORED, STRING, Schema, TextFieldIndexing,
+        TextOptions, Value,
     },
-    tokenizer::{SimpleTokenizer, TokenStream, Tokenizer},
+    tokenizer::{TextAnalyzer, Token, TokenFilter, TokenStream, Tokenizer},
 };
 use tantivy_jieba::JiebaTokenizer;
 use time::OffsetDateTime;
-use tracing::{debug, error};
-use vaultify::VAULTIFY;
-use whichlang::{Lang, detect_language};
+use tracing::{debug, error, warn};
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+use vaultify::{RunInfo, VAULTIFY};
+
+use crate::cancel::{CancelToken, CancellableQuery};
+use crate::rank::{RankWeights, Ranker, path_depth};
+
+pub const SCHEMA_VERSION: &str = "11";
+
+const NAME_START: &str = "\u{2}";
+const NAME_END: &str = "\u{3}";
+const MAX_GRAM: usize = 3;
+const FUZZY_SCORE: f32 = 0.5;
+const RUN_BOOST_LIMIT: usize = 1000;
+/// Stands in for the modification time of a path indexed more than once, so a
+/// reconcile replaces its documents with a single one.
+const DUPLICATE: i64 = i64::MIN;
+
+/// Makes `TANTIVY_INDEX` open an existing index without taking the writer
+/// lock, so another process may keep indexing. Must be set before first use.
+pub static READ_ONLY: AtomicBool = AtomicBool::new(false);
 
 pub static TANTIVY_INDEX: Lazy<TantivyIndex> = Lazy::new(|| {
     debug!("initializing tantivy");
     TantivyIndex::new().unwrap()
 });
 
+/// What the index holds about a path, as read back for comparing with disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexedPath {
+    pub modified: i64,
+    pub is_dir: bool,
+}
+
+pub struct FileMeta {
+    pub size: u64,
+    pub modified: i64,
+    pub created: i64,
+    pub is_dir: bool,
+    pub extension: String,
+}
+
+impl FileMeta {
+    pub fn new(name: &str, metadata: Option<&fs::Metadata>) -> Self {
+        let to_secs = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64)
+                .unwrap_or(0)
+        };
+
+        match metadata {
+            Some(metadata) => FileMeta {
+                size: metadata.len(),
+                modified: to_secs(metadata.modified()),
+                created: to_secs(metadata.created()),
+                is_dir: metadata.is_dir(),
+                extension: get_extension(name),
+            },
+            None => FileMeta {
+                size: 0,
+                modified: 0,
+                created: 0,
+                is_dir: false,
+                extension: get_extension(name),
+            },
+        }
+    }
+}
+
 pub struct TantivyIndex {
     // schema: Schema,
     name_field: Field,
+    name_gram_field: Field,
+    name_pinyin_field: Field,
+    name_initials_field: Field,
+    name_raw_field: Field,
+    name_sort_field: Field,
     path_field: Field,
+    path_sort_field: Field,
+    full_path_field: Field,
+    parent_field: Field,
+    size_field: Field,
+    modified_field: Field,
+    created_field: Field,
+    is_dir_field: Field,
+    extension_field: Field,
+    depth_field: Field,
+    in_home_field: Field,
+    home_dir: String,
     index: Index,
-    index_writer: Mutex<IndexWriter>,
+    /// Absent when the index was opened read-only.
+    index_writer: Mutex<Option<IndexWriter>>,
     index_reader: IndexReader,
 }
 
@@ -44,14 +136,49 @@ impl TantivyIndex {
                 .set_tokenizer("mixed")
                 .set_index_option(IndexRecordOption::WithFreqsAndPositions),
         );
-        let name_field = schema_builder.add_text_field("name", name_options);
+        let name_field = schema_builder.add_text_field("name", name_options.clone());
+        let name_gram_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("substring")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let name_gram_field = schema_builder.add_text_field("name_gram", name_gram_options.clone());
+        let name_pinyin_field = schema_builder.add_text_field(
+            "name_pinyin",
+            name_gram_options.clone().set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer("pinyin")
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            ),
+        );
+        let name_initials_field = schema_builder.add_text_field(
+            "name_initials",
+            name_gram_options.set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer("pinyin_initials")
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            ),
+        );
+        let name_raw_field = schema_builder.add_text_field("name_raw", STRING);
+        let name_sort_field = schema_builder.add_text_field("name_sort", STRING | FAST);
         let path_field = schema_builder.add_bytes_field("path", INDEXED | STORED);
-        // schema_builder.add_bytes_field("if_folder", STORED);
+        let path_sort_field = schema_builder.add_text_field("path_sort", STRING | FAST);
+        let full_path_field = schema_builder.add_text_field("full_path", name_options);
+        let parent_field = schema_builder.add_text_field("parent", STRING);
+        let size_field = schema_builder.add_u64_field("size", INDEXED | STORED | FAST);
+        let modified_field = schema_builder.add_i64_field("modified", INDEXED | STORED | FAST);
+        let created_field = schema_builder.add_i64_field("created", INDEXED | STORED | FAST);
+        let is_dir_field = schema_builder.add_bool_field("is_dir", INDEXED | STORED | FAST);
+        let extension_field = schema_builder.add_text_field("extension", STRING | STORED | FAST);
+        let depth_field = schema_builder.add_u64_field("depth", FAST);
+        let in_home_field = schema_builder.add_bool_field("in_home", FAST);
         let schema = schema_builder.build();
 
         let index_path = VAULTIFY.get("tantivy_path").unwrap();
+        let read_only = READ_ONLY.load(Ordering::SeqCst);
         let index = match Index::open_in_dir(&index_path) {
             Ok(index) => index,
+            Err(e) if read_only => return Err(e.into()),
             Err(_) => {
                 if let Err(e) = fs::create_dir_all(&index_path) {
                     error!("Failed to create directory at {}: {}", &index_path, e);
@@ -72,10 +199,23 @@ impl TantivyIndex {
             }
         };
 
-        let mixed_tokenizer = MixedTokenizer::new();
+        let mixed_tokenizer = TextAnalyzer::builder(MixedTokenizer::new())
+            .filter(FoldFilter)
+            .build();
         index.tokenizers().register("mixed", mixed_tokenizer);
+        index.tokenizers().register("substring", SubstringTokenizer);
+        index
+            .tokenizers()
+            .register("pinyin", PinyinTokenizer { initials: false });
+        index
+            .tokenizers()
+            .register("pinyin_initials", PinyinTokenizer { initials: true });
 
-        let index_writer = index.writer(50_000_000)?;
+        let index_writer = if read_only {
+            None
+        } else {
+            Some(index.writer(50_000_000)?)
+        };
         let index_reader = index
             .reader_builder()
             .reload_policy(ReloadPolicy::OnCommitWithDelay)
@@ -85,71 +225,717 @@ impl TantivyIndex {
         Ok(TantivyIndex {
             // schema,
             name_field,
+            name_gram_field,
+            name_pinyin_field,
+            name_initials_field,
+            name_raw_field,
+            name_sort_field,
             path_field,
+            path_sort_field,
+            full_path_field,
+            parent_field,
+            size_field,
+            modified_field,
+            created_field,
+            is_dir_field,
+            extension_field,
+            depth_field,
+            in_home_field,
+            home_dir: VAULTIFY.get("home_dir").unwrap_or_default(),
             index,
             index_writer: Mutex::new(index_writer),
             index_reader,
         })
     }
 
-    pub fn add(&self, name: &str, path: &str) -> Result<(), TantivyError> {
-        let writer_guard = self.index_writer.lock().unwrap();
-        writer_guard.add_document(doc!(
+    pub fn add(&self, name: &str, path: &str, meta: &FileMeta) -> Result<(), TantivyError> {
+        let parent = Path::new(path)
+            .parent()
+            .and_then(|parent| parent.to_str())
+            .unwrap_or("");
+        let mut document = doc!(
             self.name_field => name,
+            self.name_gram_field => name,
+            self.name_raw_field => name,
+            self.name_sort_field => fold(name),
             self.path_field => path.as_bytes(),
-            // self.schema.get_field("is_folder").unwrap() => is_folder,
-        ))?;
+            self.path_sort_field => path,
+            self.full_path_field => path,
+            self.parent_field => parent,
+            self.size_field => meta.size,
+            self.modified_field => meta.modified,
+            self.created_field => meta.created,
+            self.is_dir_field => meta.is_dir,
+            self.extension_field => meta.extension.as_str(),
+            self.depth_field => path_depth(path),
+            self.in_home_field => !self.home_dir.is_empty() && Path::new(path).starts_with(&self.home_dir),
+        );
+        if name.chars().any(|c| Script::of(c) == Script::Cjk) {
+            document.add_text(self.name_pinyin_field, name);
+            document.add_text(self.name_initials_field, name);
+        }
+        self.with_writer(|writer| writer.add_document(document))?;
         Ok(())
     }
 
     pub fn delete(&self, path: &str) -> Result<()> {
-        let writer_guard = self.index_writer.lock().unwrap();
-        writer_guard.delete_term(Term::from_field_bytes(self.path_field, path.as_bytes()));
+        self.with_writer(|writer| {
+            Ok(writer.delete_term(Term::from_field_bytes(self.path_field, path.as_bytes())))
+        })?;
         Ok(())
     }
 
     pub fn commit(&self) -> Result<(), TantivyError> {
-        let mut writer_guard = self.index_writer.lock().unwrap();
-        writer_guard.commit()?;
+        self.with_writer(|writer| writer.commit())?;
         Ok(())
     }
 
-    pub fn search(&self, query: &str) -> Result<Vec<Something>, TantivyError> {
-        debug!("Searching for {}", query);
+    fn with_writer<T>(
+        &self,
+        write: impl FnOnce(&mut IndexWriter) -> Result<T, TantivyError>,
+    ) -> Result<T, TantivyError> {
+        let mut writer_guard = self.index_writer.lock().unwrap();
+        match writer_guard.as_mut() {
+            Some(writer) => write(writer),
+            None => Err(TantivyError::InvalidArgument(
+                "index is opened read-only".to_string(),
+            )),
+        }
+    }
+
+    /// Returns the page of hits the request asks for, together with the total
+    /// number of hits. The page is collected in one pass whose heap holds up to
+    /// `offset + limit` hits but never more than there are. Fails with
+    /// `QueryError::PageOutOfRange` when `offset + limit` overflows and with
+    /// `QueryError::Cancelled` once `cancel` fires.
+    pub fn search(
+        &self,
+        request: &SearchRequest,
+        cancel: &CancelToken,
+    ) -> Result<(Vec<SearchHit>, usize), QueryError> {
+        debug!("Searching for {} from {}", request.query, request.offset);
+        let SearchRequest {
+            sort,
+            offset,
+            limit,
+            ..
+        } = *request;
+        let end = offset
+            .checked_add(limit)
+            .ok_or(QueryError::PageOutOfRange)?;
         let mut results = vec![];
         let searcher = self.index_reader.searcher();
-        let query_parser = QueryParser::for_index(&self.index, vec![self.name_field]);
-        let query = query_parser.parse_query(query)?;
+        let expr = query::parse_with(&request.query, request.options)?;
+        let weights = RankWeights::load();
+        let query = self
+            .build_query(&expr, &weights, cancel)
+            .map_err(index_error)?;
+        let query = self.boost_runs(query, weights.runs);
+        let query = CancellableQuery::new(query, cancel.clone());
 
-        let top_docs: Vec<(f32, tantivy::DocAddress)> =
-            searcher.search(&query, &TopDocs::with_limit(100))?;
-        debug!("Found {} results", top_docs.len());
+        let ranker = Ranker::new(
+            weights,
+            &self.home_dir,
+            OffsetDateTime::now_utc().unix_timestamp(),
+        );
+        let total = searcher.search(&query, &Count).map_err(index_error)?;
+        let end = end.min(total);
+        let addresses = if offset < end {
+            self.collect_page(&searcher, &query, sort, ranker, offset, end)
+                .map_err(index_error)?
+        } else {
+            vec![]
+        };
+        if cancel.is_cancelled() {
+            return Err(QueryError::Cancelled);
+        }
+        debug!("Found {} results", total);
 
-        for (_id, (_score, doc_address)) in top_docs.iter().enumerate() {
-            let retrieved_doc: TantivyDocument = searcher.doc(*doc_address)?;
+        let runs = self.runs();
+        for doc_address in addresses {
+            let retrieved_doc: TantivyDocument = searcher.doc(doc_address).map_err(index_error)?;
 
-            let path: String = retrieved_doc
-                .get_first(self.path_field)
-                .unwrap()
-                .as_bytes()
-                .map(|x| String::from_utf8(x.to_vec()).unwrap())
-                .unwrap();
+            results.push(self.to_hit(&retrieved_doc, &runs));
+        }
+        Ok((results, total))
+    }
 
-            results.push(Something {
-                name: "".into(),
-                path: path.into(),
-                size: 0.0,
-                last_modified_date: OffsetDateTime::now_utc().date(),
-                class: "".into(),
-            });
+    /// Collects the addresses of the hits from `start` up to `end` in `sort`
+    /// order. `end` must be past `start`.
+    fn collect_page(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        sort: Sort,
+        ranker: Ranker,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<DocAddress>, TantivyError> {
+        let order = if sort.descending {
+            Order::Desc
+        } else {
+            Order::Asc
+        };
+        let top_docs = TopDocs::with_limit(end - start).and_offset(start);
+        match sort.key {
+            SortKey::Relevance => top_addresses(
+                searcher,
+                query,
+                top_docs.tweak_score(move |segment: &SegmentReader| {
+                    let fast_fields = segment.fast_fields();
+                    let depth = fast_fields.u64("depth").ok();
+                    let in_home = fast_fields.bool("in_home").ok();
+                    let modified = fast_fields.i64("modified").ok();
+                    move |doc: DocId, score: Score| {
+                        ranker.score(
+                            score,
+                            depth.as_ref().and_then(|c| c.first(doc)).unwrap_or(0),
+                            in_home.as_ref().and_then(|c| c.first(doc)).unwrap_or(false),
+                            modified.as_ref().and_then(|c| c.first(doc)).unwrap_or(0),
+                        )
+                    }
+                }),
+            ),
+            SortKey::Size => top_addresses(
+                searcher,
+                query,
+                top_docs.order_by_fast_field::<u64>("size", order),
+            ),
+            SortKey::Modified => top_addresses(
+                searcher,
+                query,
+                top_docs.order_by_fast_field::<i64>("modified", order),
+            ),
+            SortKey::Name => top_addresses(
+                searcher,
+                query,
+                top_docs.order_by_string_fast_field("name_sort", order),
+            ),
+            SortKey::Path => top_addresses(
+                searcher,
+                query,
+                top_docs.order_by_string_fast_field("path_sort", order),
+            ),
+            SortKey::Kind => top_addresses(
+                searcher,
+                query,
+                top_docs.order_by_string_fast_field("extension", order),
+            ),
+            SortKey::RunCount | SortKey::DateRun => {
+                let value: fn(&RunInfo) -> i64 = match sort.key {
+                    SortKey::RunCount => |run| run.count as i64,
+                    _ => |run| run.last_run,
+                };
+                let sign = if sort.descending { 1 } else { -1 };
+                let runs = self.runs();
+                top_addresses(
+                    searcher,
+                    query,
+                    top_docs.custom_score(move |segment: &SegmentReader| {
+                        // the history is keyed by path, the column by term ordinal
+                        let paths = segment.fast_fields().str("path_sort").ok().flatten();
+                        let values: HashMap<u64, i64> = paths
+                            .as_ref()
+                            .map(|paths| {
+                                runs.iter()
+                                    .filter_map(|(path, run)| {
+                                        let ord = paths.dictionary().term_ord(path).ok()??;
+                                        Some((ord, value(run)))
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        move |doc: DocId| {
+                            let ord = paths.as_ref().and_then(|paths| paths.term_ords(doc).next());
+                            sign * ord.and_then(|ord| values.get(&ord)).copied().unwrap_or(0)
+                        }
+                    }),
+                )
+            }
+        }
+    }
+
+    /// Adds the open count of frequently opened files to their score.
+    fn boost_runs(&self, query: Box<dyn Query>, weight: f32) -> Box<dyn Query> {
+        let runs = self.runs();
+        if runs.is_empty() || weight == 0.0 {
+            return query;
+        }
+        let mut runs: Vec<(&String, &RunInfo)> = runs.iter().collect();
+        runs.sort_by_key(|(_, run)| Reverse(run.count));
+
+        let mut clauses = vec![(Occur::Must, query)];
+        for (path, run) in runs.into_iter().take(RUN_BOOST_LIMIT) {
+            let term_query = TermQuery::new(
+                Term::from_field_bytes(self.path_field, path.as_bytes()),
+                IndexRecordOption::Basic,
+            );
+            let boost = weight * (1.0 + run.count as f32).ln();
+            clauses.push((
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(Box::new(term_query), boost)),
+            ));
+        }
+        Box::new(BooleanQuery::new(clauses))
+    }
+
+    fn runs(&self) -> Arc<HashMap<String, RunInfo>> {
+        VAULTIFY.list_runs().unwrap_or_else(|e| {
+            warn!("failed to read run history: {}", e);
+            Arc::default()
+        })
+    }
+
+    /// Matches files by their run history. Files never opened have no history,
+    /// so they match only when `matches_unrun` is set.
+    fn run_query(&self, matches: impl Fn(&RunInfo) -> bool, matches_unrun: bool) -> Box<dyn Query> {
+        let runs = self.runs();
+        let paths = |keep: bool| {
+            TermSetQuery::new(
+                runs.iter()
+                    .filter(|(_, run)| matches(run) == keep)
+                    .map(|(path, _)| Term::from_field_bytes(self.path_field, path.as_bytes())),
+            )
+        };
+        if matches_unrun {
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery)),
+                (Occur::MustNot, Box::new(paths(false))),
+            ]))
+        } else {
+            Box::new(paths(true))
+        }
+    }
+
+    fn build_query(
+        &self,
+        expr: &Expr,
+        weights: &RankWeights,
+        cancel: &CancelToken,
+    ) -> Result<Box<dyn Query>, TantivyError> {
+        let query: Box<dyn Query> = match expr {
+            Expr::Term(text) => self.name_query(text, weights, cancel)?,
+            Expr::Modifier(modifier) => self.modifier_query(modifier, weights, cancel)?,
+            Expr::Not(inner) => Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery)),
+                (Occur::MustNot, self.build_query(inner, weights, cancel)?),
+            ])),
+            Expr::And(items) => {
+                let mut clauses = vec![];
+                for item in items {
+                    match item {
+                        Expr::Not(inner) => clauses
+                            .push((Occur::MustNot, self.build_query(inner, weights, cancel)?)),
+                        _ => clauses.push((Occur::Must, self.build_query(item, weights, cancel)?)),
+                    }
+                }
+                // tantivy matches nothing for purely negative boolean queries
+                if clauses.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+                    clauses.push((Occur::Must, Box::new(AllQuery)));
+                }
+                Box::new(BooleanQuery::new(clauses))
+            }
+            Expr::Or(items) => {
+                let mut clauses = vec![];
+                for item in items {
+                    clauses.push((Occur::Should, self.build_query(item, weights, cancel)?));
+                }
+                Box::new(BooleanQuery::new(clauses))
+            }
+        };
+        Ok(query)
+    }
+
+    fn modifier_query(
+        &self,
+        modifier: &Modifier,
+        weights: &RankWeights,
+        cancel: &CancelToken,
+    ) -> Result<Box<dyn Query>, TantivyError> {
+        let query: Box<dyn Query> = match modifier {
+            Modifier::Ext(extensions) => Box::new(BooleanQuery::new(
+                extensions
+                    .iter()
+                    .map(|ext| {
+                        let query: Box<dyn Query> = Box::new(TermQuery::new(
+                            Term::from_field_text(self.extension_field, ext),
+                            IndexRecordOption::Basic,
+                        ));
+                        (Occur::Should, query)
+                    })
+                    .collect(),
+            )),
+            Modifier::Size((lower, upper)) => Box::new(RangeQuery::new(
+                map_bound(lower, |v| Term::from_field_u64(self.size_field, v)),
+                map_bound(upper, |v| Term::from_field_u64(self.size_field, v)),
+            )),
+            Modifier::DateModified((lower, upper)) => Box::new(RangeQuery::new(
+                map_bound(lower, |v| Term::from_field_i64(self.modified_field, v)),
+                map_bound(upper, |v| Term::from_field_i64(self.modified_field, v)),
+            )),
+            Modifier::DateCreated((lower, upper)) => Box::new(RangeQuery::new(
+                map_bound(lower, |v| Term::from_field_i64(self.created_field, v)),
+                map_bound(upper, |v| Term::from_field_i64(self.created_field, v)),
+            )),
+            Modifier::RunCount(range) => {
+                self.run_query(|run| range.contains(&run.count), range.contains(&0))
+            }
+            Modifier::DateRun(range) => self.run_query(|run| range.contains(&run.last_run), false),
+            Modifier::Path(text) => self.text_query(self.full_path_field, text)?,
+            Modifier::Folder(name) => self.kind_query(true, name.as_deref(), weights, cancel)?,
+            Modifier::File(name) => self.kind_query(false, name.as_deref(), weights, cancel)?,
+            Modifier::Parent(dir) => {
+                let dir = match dir.trim_end_matches('/') {
+                    "" => "/",
+                    dir => dir,
+                };
+                Box::new(TermQuery::new(
+                    Term::from_field_text(self.parent_field, dir),
+                    IndexRecordOption::Basic,
+                ))
+            }
+            Modifier::Fuzzy(text) => Box::new(BooleanQuery::new(vec![
+                (Occur::Should, self.name_query(text, weights, cancel)?),
+                (
+                    Occur::Should,
+                    Box::new(ConstScoreQuery::new(self.fuzzy_query(text)?, FUZZY_SCORE)),
+                ),
+            ])),
+            Modifier::Regex(pattern) => self.regex_query(pattern, cancel)?,
+            Modifier::Case(text) => {
+                let pattern = if text.contains(['*', '?']) {
+                    glob_to_regex(text, true)
+                } else {
+                    regex::escape(text)
+                };
+                Box::new(BooleanQuery::new(vec![
+                    (Occur::Must, self.name_query(text, weights, cancel)?),
+                    (Occur::Must, self.regex_query(&pattern, cancel)?),
+                ]))
+            }
+        };
+        Ok(query)
+    }
+
+    fn kind_query(
+        &self,
+        is_dir: bool,
+        name: Option<&str>,
+        weights: &RankWeights,
+        cancel: &CancelToken,
+    ) -> Result<Box<dyn Query>, TantivyError> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(
+            Occur::Must,
+            Box::new(TermQuery::new(
+                Term::from_field_bool(self.is_dir_field, is_dir),
+                IndexRecordOption::Basic,
+            )),
+        )];
+        if let Some(name) = name {
+            clauses.push((Occur::Must, self.name_query(name, weights, cancel)?));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// Matches names containing `text` anywhere, ranking exact and prefix
+    /// matches first and whole-token matches above plain substrings. Text with
+    /// `*` or `?` wildcards must match the whole name instead.
+    fn name_query(
+        &self,
+        text: &str,
+        weights: &RankWeights,
+        cancel: &CancelToken,
+    ) -> Result<Box<dyn Query>, TantivyError> {
+        if text.contains(['*', '?']) {
+            return self.regex_query(&glob_to_regex(text, false), cancel);
+        }
+        let units: Vec<String> = gram_units(text).into_iter().map(|unit| unit.2).collect();
+        if units.is_empty() {
+            return Ok(Box::new(AllQuery));
+        }
+        let prefix: Vec<String> = std::iter::once(NAME_START.to_string())
+            .chain(units.iter().cloned())
+            .collect();
+        let exact: Vec<String> = prefix
+            .iter()
+            .cloned()
+            .chain(std::iter::once(NAME_END.to_string()))
+            .collect();
+
+        let name_query: Box<dyn Query> = Box::new(BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(
+                    self.gram_query(self.name_gram_field, &units),
+                    1.0,
+                )),
+            ),
+            (
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(
+                    self.gram_query(self.name_gram_field, &prefix),
+                    weights.prefix,
+                )),
+            ),
+            (
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(
+                    self.gram_query(self.name_gram_field, &exact),
+                    weights.exact,
+                )),
+            ),
+            (Occur::Should, self.text_query(self.name_field, text)?),
+        ]));
+        if !text.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Ok(name_query);
+        }
+
+        // Latin letters may also be the pinyin spelling or initials of a
+        // Chinese name.
+        Ok(Box::new(BooleanQuery::new(vec![
+            (Occur::Should, name_query),
+            (
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(
+                    self.gram_query(self.name_pinyin_field, &units),
+                    1.0,
+                )),
+            ),
+            (
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(
+                    self.gram_query(self.name_initials_field, &units),
+                    1.0,
+                )),
+            ),
+        ])))
+    }
+
+    /// Matches the contiguous run of `units` in a gram field by chaining grams
+    /// at their relative positions.
+    fn gram_query(&self, field: Field, units: &[String]) -> Box<dyn Query> {
+        let gram_term = |start: usize| {
+            let end = (start + MAX_GRAM).min(units.len());
+            Term::from_field_text(field, &units[start..end].concat())
+        };
+        if units.len() <= MAX_GRAM {
+            return Box::new(TermQuery::new(gram_term(0), IndexRecordOption::Basic));
+        }
+
+        let last = units.len() - MAX_GRAM;
+        let mut offsets: Vec<usize> = (0..last).step_by(MAX_GRAM).collect();
+        offsets.push(last);
+        Box::new(PhraseQuery::new_with_offset(
+            offsets
+                .into_iter()
+                .map(|offset| (offset, gram_term(offset)))
+                .collect(),
+        ))
+    }
+
+    /// Requires every name token of `text` within a Levenshtein distance that
+    /// grows with the token length.
+    fn fuzzy_query(&self, text: &str) -> Result<Box<dyn Query>, TantivyError> {
+        let mut analyzer = self.index.tokenizer_for_field(self.name_field)?;
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![];
+        analyzer.token_stream(text).process(&mut |token| {
+            let distance = match token.text.chars().count() {
+                0..=2 => 0,
+                3..=5 => 1,
+                _ => 2,
+            };
+            let term = Term::from_field_text(self.name_field, &token.text);
+            clauses.push((
+                Occur::Must,
+                Box::new(FuzzyTermQuery::new(term, distance, true)),
+            ));
+        });
+
+        if clauses.is_empty() {
+            return Ok(Box::new(AllQuery));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// Runs the regex over the term dictionary of the raw name or path field.
+    /// Patterns tantivy's automaton cannot compile, such as word boundaries,
+    /// fall back to scanning the dictionary with the full regex engine.
+    fn regex_query(
+        &self,
+        pattern: &str,
+        cancel: &CancelToken,
+    ) -> Result<Box<dyn Query>, TantivyError> {
+        let field = if pattern.contains('/') {
+            self.path_field
+        } else {
+            self.name_raw_field
+        };
+        let term_query = match to_term_pattern(pattern) {
+            Some(term_pattern) => RegexQuery::from_pattern(&term_pattern, field),
+            None => Err(TantivyError::InvalidArgument(
+                "anchors inside an alternation".to_string(),
+            )),
+        };
+        match term_query {
+            Ok(query) => Ok(Box::new(query)),
+            Err(e) => {
+                debug!("regex {} needs a term scan: {}", pattern, e);
+                self.regex_scan_query(pattern, field, cancel)
+            }
+        }
+    }
+
+    fn regex_scan_query(
+        &self,
+        pattern: &str,
+        field: Field,
+        cancel: &CancelToken,
+    ) -> Result<Box<dyn Query>, TantivyError> {
+        let regex =
+            regex::Regex::new(pattern).map_err(|e| TantivyError::InvalidArgument(e.to_string()))?;
+        let searcher = self.index_reader.searcher();
+        let mut terms = vec![];
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            while stream.advance() {
+                // the scan is abandoned once superseded; search reports the cancellation
+                if cancel.is_cancelled() {
+                    return Ok(Box::new(EmptyQuery));
+                }
+                let Ok(text) = std::str::from_utf8(stream.key()) else {
+                    continue;
+                };
+                if regex.is_match(text) {
+                    terms.push(if field == self.path_field {
+                        Term::from_field_bytes(field, stream.key())
+                    } else {
+                        Term::from_field_text(field, text)
+                    });
+                }
+            }
+        }
+        Ok(Box::new(TermSetQuery::new(terms)))
+    }
+
+    /// Requires every token the field's analyzer produces for `text`.
+    fn text_query(&self, field: Field, text: &str) -> Result<Box<dyn Query>, TantivyError> {
+        let mut analyzer = self.index.tokenizer_for_field(field)?;
+        let mut terms = vec![];
+        analyzer
+            .token_stream(text)
+            .process(&mut |token| terms.push(Term::from_field_text(field, &token.text)));
+
+        if terms.is_empty() {
+            return Ok(Box::new(AllQuery));
+        }
+        Ok(Box::new(BooleanQuery::new(
+            terms
+                .into_iter()
+                .map(|term| {
+                    let query: Box<dyn Query> =
+                        Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+                    (Occur::Must, query)
+                })
+                .collect(),
+        )))
+    }
+
+    fn to_hit(&self, doc: &TantivyDocument, runs: &HashMap<String, RunInfo>) -> SearchHit {
+        let path: String = doc
+            .get_first(self.path_field)
+            .unwrap()
+            .as_bytes()
+            .map(|x| String::from_utf8(x.to_vec()).unwrap())
+            .unwrap();
+
+        let name = path
+            .rsplit('/')
+            .find(|part| !part.is_empty())
+            .unwrap_or("")
+            .to_string();
+
+        let size = doc
+            .get_first(self.size_field)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let is_dir = doc
+            .get_first(self.is_dir_field)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let extension = doc
+            .get_first(self.extension_field)
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let modified = doc
+            .get_first(self.modified_field)
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+
+        let class = if !extension.is_empty() {
+            extension.to_string()
+        } else if is_dir {
+            "folder".to_string()
+        } else {
+            "file".to_string()
+        };
+
+        let run = runs.get(&path);
+        SearchHit {
+            name,
+            class,
+            size,
+            modified,
+            is_dir,
+            run_count: run.map_or(0, |run| run.count),
+            date_run: run.map_or(0, |run| run.last_run),
+            path: path.into(),
         }
-        Ok(results)
     }
 
     pub fn get_num_docs(&self) -> u64 {
         self.index_reader.searcher().num_docs()
     }
 
+    /// Maps every indexed path to the modification time and kind stored with
+    /// it, read from the fast fields rather than the document store.
+    pub fn indexed_paths(&self) -> Result<HashMap<String, IndexedPath>> {
+        let searcher = self.index_reader.searcher();
+        let mut paths = HashMap::with_capacity(searcher.num_docs() as usize);
+        for segment_reader in searcher.segment_readers() {
+            let fast_fields = segment_reader.fast_fields();
+            let Some(path_column) = fast_fields.str("path_sort")? else {
+                continue;
+            };
+            let modified_column = fast_fields.i64("modified")?;
+            let is_dir_column = fast_fields.bool("is_dir")?;
+            // streaming the dictionary once beats looking up each document's term
+            let mut terms = Vec::with_capacity(path_column.num_terms());
+            let mut stream = path_column.dictionary().stream()?;
+            while stream.advance() {
+                terms.push(String::from_utf8_lossy(stream.key()).to_string());
+            }
+            for doc_id in segment_reader.doc_ids_alive() {
+                let Some(path) = path_column
+                    .term_ords(doc_id)
+                    .next()
+                    .and_then(|ord| terms.get(ord as usize))
+                else {
+                    continue;
+                };
+                let indexed = IndexedPath {
+                    modified: modified_column.first(doc_id).unwrap_or(0),
+                    is_dir: is_dir_column.first(doc_id).unwrap_or(false),
+                };
+                paths
+                    .entry(path.clone())
+                    .and_modify(|stored: &mut IndexedPath| stored.modified = DUPLICATE)
+                    .or_insert(indexed);
+            }
+        }
+        Ok(paths)
+    }
+
     pub fn list_all(&self) -> Result<()> {
         let searcher = self.index_reader.searcher();
 
@@ -174,27 +960,330 @@ impl TantivyIndex {
 
 #[derive(Clone)]
 struct MixedTokenizer {
-    default_tokenizer: SimpleTokenizer,
     jieba_tokenizer: JiebaTokenizer,
 }
 
 impl MixedTokenizer {
     fn new() -> Self {
         MixedTokenizer {
-            default_tokenizer: SimpleTokenizer::default(),
             jieba_tokenizer: tantivy_jieba::JiebaTokenizer {},
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Script {
+    Cjk,
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Self {
+        match c {
+            '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{20000}'..='\u{2fa1f}' => Script::Cjk,
+            _ => Script::Other,
+        }
+    }
+}
+
+/// Splits text into maximal runs of a single script, returning each run with
+/// its byte offset.
+fn script_runs(text: &str) -> Vec<(usize, &str, Script)> {
+    let mut runs = vec![];
+    let mut start = 0;
+    let mut current = None;
+    for (offset, c) in text.char_indices() {
+        let script = Script::of(c);
+        match current {
+            Some(previous) if previous == script => {}
+            Some(previous) => {
+                runs.push((start, &text[start..offset], previous));
+                start = offset;
+                current = Some(script);
+            }
+            None => current = Some(script),
+        }
+    }
+    if let Some(script) = current {
+        runs.push((start, &text[start..], script));
+    }
+    runs
+}
+
+/// Tokenizes each script run separately, CJK runs with jieba and everything
+/// else with [`word_tokens`]. Positions keep increasing across runs so phrase
+/// queries span script boundaries.
 impl Tokenizer for MixedTokenizer {
-    type TokenStream<'a> = Box<dyn TokenStream + 'a>;
+    type TokenStream<'a> = VecTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        let mut tokens: Vec<Token> = vec![];
+        let mut next_position = 0;
+        for (start, run, script) in script_runs(text) {
+            let mut stream: Box<dyn TokenStream> = match script {
+                Script::Cjk => Box::new(self.jieba_tokenizer.token_stream(run)),
+                Script::Other => Box::new(VecTokenStream::new(word_tokens(run))),
+            };
+
+            let base = next_position;
+            stream.process(&mut |token| {
+                let position = base + token.position;
+                next_position = next_position.max(position + 1);
+                tokens.push(Token {
+                    offset_from: start + token.offset_from,
+                    offset_to: start + token.offset_to,
+                    position,
+                    text: token.text.clone(),
+                    position_length: token.position_length,
+                });
+            });
+        }
+        VecTokenStream::new(tokens)
+    }
+}
+
+fn is_connector(c: char) -> bool {
+    matches!(c, '_' | '-' | '.')
+}
+
+/// Splits text into words of alphanumerics and connectors. A word made of
+/// several parts is emitted whole and then once per camelCase, connector or
+/// letter/digit part, with the whole word sharing the first part's position.
+fn word_tokens(text: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut position = 0;
+    let push = |tokens: &mut Vec<Token>, from: usize, to: usize, position: usize| {
+        tokens.push(Token {
+            offset_from: from,
+            offset_to: to,
+            position,
+            text: text[from..to].to_string(),
+            position_length: 1,
+        });
+    };
+
+    let mut start = 0;
+    for (offset, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if c.is_alphanumeric() || is_connector(c) {
+            continue;
+        }
+        let segment = &text[start..offset];
+        let trimmed = segment.trim_matches(is_connector);
+        if !trimmed.is_empty() {
+            let from = start + (segment.len() - segment.trim_start_matches(is_connector).len());
+            let parts = word_parts(trimmed);
+            if parts.len() > 1 {
+                push(&mut tokens, from, from + trimmed.len(), position);
+            }
+            for (part_from, part_to) in &parts {
+                push(&mut tokens, from + part_from, from + part_to, position);
+                position += 1;
+            }
+        }
+        start = offset + c.len_utf8();
+    }
+    tokens
+}
+
+/// Returns the byte ranges of the parts of a word, split at connectors,
+/// lower-to-upper case changes, acronym ends (`HTTPServer`) and letter/digit
+/// transitions.
+fn word_parts(word: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut parts = vec![];
+    let mut start = None;
+    for (i, &(offset, c)) in chars.iter().enumerate() {
+        if is_connector(c) {
+            if let Some(from) = start.take() {
+                parts.push((from, offset));
+            }
+            continue;
+        }
+        if let Some(from) = start {
+            let prev = chars[i - 1].1;
+            let next = chars.get(i + 1).map(|&(_, c)| c);
+            let boundary = (prev.is_lowercase() && c.is_uppercase())
+                || (prev.is_uppercase()
+                    && c.is_uppercase()
+                    && next.is_some_and(|n| n.is_lowercase()))
+                || (prev.is_numeric() != c.is_numeric());
+            if boundary {
+                parts.push((from, offset));
+                start = Some(offset);
+            }
+        } else {
+            start = Some(offset);
+        }
+    }
+    if let Some(from) = start {
+        parts.push((from, word.len()));
+    }
+    parts
+}
+
+/// Splits text into folded per-character units together with their byte
+/// offsets, the alphabet the substring grams are built from. Characters that
+/// fold away entirely, such as decomposed accents, are dropped.
+fn gram_units(text: &str) -> Vec<(usize, usize, String)> {
+    text.char_indices()
+        .map(|(offset, c)| {
+            let unit = fold(c.encode_utf8(&mut [0; 4]));
+            (offset, offset + c.len_utf8(), unit)
+        })
+        .filter(|unit| !unit.2.is_empty())
+        .collect()
+}
+
+/// Lowercases and strips diacritics after compatibility decomposition, so
+/// `RÉSUMÉ`, `résumé` and a decomposed `résumé` all fold to `resume`.
+fn fold(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .nfc()
+        .collect()
+}
+
+#[derive(Clone)]
+struct FoldFilter;
+
+impl TokenFilter for FoldFilter {
+    type Tokenizer<T: Tokenizer> = FoldTokenizer<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> Self::Tokenizer<T> {
+        FoldTokenizer(tokenizer)
+    }
+}
+
+#[derive(Clone)]
+struct FoldTokenizer<T>(T);
+
+impl<T: Tokenizer> Tokenizer for FoldTokenizer<T> {
+    type TokenStream<'a> = FoldTokenStream<T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        FoldTokenStream(self.0.token_stream(text))
+    }
+}
+
+struct FoldTokenStream<T>(T);
+
+impl<T: TokenStream> TokenStream for FoldTokenStream<T> {
+    fn advance(&mut self) -> bool {
+        if !self.0.advance() {
+            return false;
+        }
+        let token = self.0.token_mut();
+        token.text = fold(&token.text);
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.0.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.0.token_mut()
+    }
+}
+
+/// Emits every 1..=MAX_GRAM character gram of the name at the position of its
+/// first character, so a run of grams can be matched as a phrase. The name is
+/// wrapped in start and end markers to tell prefix and exact matches apart.
+#[derive(Clone)]
+struct SubstringTokenizer;
+
+impl Tokenizer for SubstringTokenizer {
+    type TokenStream<'a> = VecTokenStream;
 
     fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
-        match detect_language(text) {
-            Lang::Cmn => Box::new(self.jieba_tokenizer.token_stream(text)),
-            _ => Box::new(self.default_tokenizer.token_stream(text)),
+        let mut units = vec![(0, 0, NAME_START.to_string())];
+        units.extend(gram_units(text));
+        units.push((text.len(), text.len(), NAME_END.to_string()));
+
+        let mut tokens = vec![];
+        for start in 0..units.len() {
+            for end in start + 1..=(start + MAX_GRAM).min(units.len()) {
+                tokens.push(Token {
+                    offset_from: units[start].0,
+                    offset_to: units[end - 1].1,
+                    position: start,
+                    text: units[start..end]
+                        .iter()
+                        .map(|unit| unit.2.as_str())
+                        .collect(),
+                    position_length: 1,
+                });
+            }
+        }
+        VecTokenStream::new(tokens)
+    }
+}
+
+/// Grams over the name with Chinese characters spelled in pinyin, either in
+/// full or as initials, so `gouwuqingdan` and `gwqd` find `购物清单.md`.
+#[derive(Clone)]
+struct PinyinTokenizer {
+    initials: bool,
+}
+
+impl Tokenizer for PinyinTokenizer {
+    type TokenStream<'a> = VecTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        SubstringTokenizer.token_stream(&to_pinyin(text, self.initials))
+    }
+}
+
+fn to_pinyin(text: &str, initials: bool) -> String {
+    let mut spelled = String::new();
+    for (_, run, script) in script_runs(text) {
+        if script != Script::Cjk {
+            spelled.push_str(run);
+            continue;
         }
+        for c in run.chars() {
+            match c.to_pinyin() {
+                Some(pinyin) if initials => spelled.push_str(pinyin.first_letter()),
+                Some(pinyin) => spelled.push_str(pinyin.plain()),
+                None => spelled.push(c),
+            }
+        }
+    }
+    spelled
+}
+
+struct VecTokenStream {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl VecTokenStream {
+    fn new(tokens: Vec<Token>) -> Self {
+        VecTokenStream { tokens, index: 0 }
+    }
+}
+
+impl TokenStream for VecTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.index < self.tokens.len() {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.index - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.index - 1]
     }
 }
 
@@ -221,6 +1310,94 @@ pub fn get_files(
     Ok(files)
 }
 
+/// Translates a shell-style glob into an anchored regex.
+fn glob_to_regex(glob: &str, case_sensitive: bool) -> String {
+    let mut pattern = String::from(if case_sensitive { "^" } else { "^(?i)" });
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+/// Term automatons always match whole terms, so `^` and `$` anchors are
+/// dropped and unanchored ends are padded with `.*` around the grouped
+/// pattern. With a top level alternation an anchor holds for one branch only,
+/// which no whole-term pattern expresses, so there is none.
+fn to_term_pattern(pattern: &str) -> Option<String> {
+    let anchored_start = pattern.starts_with('^');
+    let mut body = pattern.strip_prefix('^').unwrap_or(pattern);
+    // a `$` preceded by an odd number of backslashes is a literal dollar
+    let anchored_end = body.strip_suffix('$').is_some_and(|before| {
+        (before.len() - before.trim_end_matches('\\').len()).is_multiple_of(2)
+    });
+    if anchored_end {
+        body = &body[..body.len() - 1];
+    }
+    if (anchored_start || anchored_end) && has_top_level_alternation(body) {
+        return None;
+    }
+    let start = if anchored_start { "" } else { ".*" };
+    let end = if anchored_end { "" } else { ".*" };
+    Some(format!("{}(?:{}){}", start, body, end))
+}
+
+/// Whether `pattern` has a `|` outside of any group or character class.
+fn has_top_level_alternation(pattern: &str) -> bool {
+    let (mut depth, mut classes, mut escaped) = (0, 0, false);
+    for c in pattern.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => classes += 1,
+            ']' if classes > 0 => classes -= 1,
+            _ if classes > 0 => {}
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '|' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn index_error(e: TantivyError) -> QueryError {
+    QueryError::Index(e.to_string())
+}
+
+/// Runs a top docs collector and drops the sort values.
+fn top_addresses<T, C>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    collector: C,
+) -> Result<Vec<DocAddress>, TantivyError>
+where
+    C: Collector<Fruit = Vec<(T, DocAddress)>>,
+{
+    let top_docs = searcher.search(query, &collector)?;
+    Ok(top_docs.into_iter().map(|(_, address)| address).collect())
+}
+
+fn map_bound<T: Copy>(bound: &Bound<T>, to_term: impl Fn(T) -> Term) -> Bound<Term> {
+    match bound {
+        Bound::Included(v) => Bound::Included(to_term(*v)),
+        Bound::Excluded(v) => Bound::Excluded(to_term(*v)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+pub fn get_extension(name: &str) -> String {
+    name.rsplit('.')
+        .next()
+        .filter(|ext| !ext.is_empty() && ext != &name)
+        .unwrap_or("")
+        .to_lowercase()
+}
+
 pub fn get_subfolders(str: &str) -> Vec<String> {
     if let Ok(paths) = fs::read_dir(str) {
         return paths
@@ -235,6 +1412,7 @@ pub fn get_subfolders(str: &str) -> Vec<String> {
 #[cfg(feature = "mock")]
 mod tests {
     use tantivy::Document;
+    use tantivy::query::QueryParser;
     use tempfile::TempDir;
 
     use super::*;
@@ -281,6 +1459,114 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_substring_tokenizer_token_stream() {
+        let mut tokens = vec![];
+        SubstringTokenizer
+            .token_stream("Ab.c")
+            .process(&mut |token| tokens.push((token.position, token.text.clone())));
+
+        assert!(tokens.contains(&(0, format!("{}ab", NAME_START))));
+        assert!(tokens.contains(&(1, "ab.".to_string())));
+        assert!(tokens.contains(&(3, ".".to_string())));
+        assert!(tokens.contains(&(4, format!("c{}", NAME_END))));
+        assert!(
+            !tokens
+                .iter()
+                .any(|(_, text)| text.chars().count() > MAX_GRAM)
+        );
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        assert_eq!(glob_to_regex("*.rs", false), r"^(?i).*\.rs$");
+        assert_eq!(glob_to_regex("*.RS", true), r"^.*\.RS$");
+        assert_eq!(
+            glob_to_regex("report-202?.xlsx", false),
+            r"^(?i)report\-202.\.xlsx$"
+        );
+        assert_eq!(
+            to_term_pattern(&glob_to_regex("*.rs", false)).unwrap(),
+            r"(?:(?i).*\.rs)"
+        );
+    }
+
+    #[test]
+    fn test_to_term_pattern() {
+        assert_eq!(to_term_pattern("foo|bar").unwrap(), ".*(?:foo|bar).*");
+        assert_eq!(to_term_pattern("^(a|b)$").unwrap(), "(?:(a|b))");
+        assert_eq!(to_term_pattern(r"^a\$").unwrap(), r"(?:a\$).*");
+        assert_eq!(to_term_pattern(r"a\\$").unwrap(), r".*(?:a\\)");
+        assert_eq!(to_term_pattern("^[|]b$").unwrap(), "(?:[|]b)");
+        assert_eq!(to_term_pattern("^a|b$"), None);
+        assert_eq!(to_term_pattern("a|b$"), None);
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("RÉSUMÉ"), "resume");
+        assert_eq!(fold("re\u{301}sume\u{301}"), "resume");
+        assert_eq!(fold("ＲＥＡＤＭＥ"), "readme");
+        assert_eq!(fold("会议纪要"), "会议纪要");
+        assert_eq!(gram_units("e\u{301}").len(), 1);
+    }
+
+    #[test]
+    fn test_mixed_tokenizer_script_runs() {
+        let text = "会议纪要_Meeting_Notes2025.txt";
+        let mut tokens = vec![];
+        MixedTokenizer::new()
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.clone()));
+
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+        for expected in ["Meeting_Notes2025.txt", "Meeting", "Notes", "2025", "txt"] {
+            assert!(texts.contains(&expected), "missing token {}", expected);
+        }
+        for token in &tokens {
+            assert_eq!(&text[token.offset_from..token.offset_to], token.text);
+        }
+        assert!(tokens.windows(2).all(|w| w[0].position <= w[1].position));
+        assert!(tokens.last().unwrap().position > tokens.first().unwrap().position);
+    }
+
+    #[test]
+    fn test_word_tokens() {
+        let texts = |text: &str| -> Vec<String> {
+            word_tokens(text)
+                .into_iter()
+                .map(|token| format!("{}@{}", token.text, token.position))
+                .collect()
+        };
+
+        assert_eq!(
+            texts("AnythingTableDelegate.rs"),
+            vec![
+                "AnythingTableDelegate.rs@0",
+                "Anything@0",
+                "Table@1",
+                "Delegate@2",
+                "rs@3"
+            ]
+        );
+        assert_eq!(
+            texts("v2ReleaseNotes.md"),
+            vec![
+                "v2ReleaseNotes.md@0",
+                "v@0",
+                "2@1",
+                "Release@2",
+                "Notes@3",
+                "md@4"
+            ]
+        );
+        assert_eq!(
+            texts("HTTPServer-log"),
+            vec!["HTTPServer-log@0", "HTTP@0", "Server@1", "log@2"]
+        );
+        assert_eq!(texts("_draft final "), vec!["draft@0", "final@1"]);
+    }
+
     #[test]
     fn test_mixed_token_search() {
         let mut schema_builder = Schema::builder();
@@ -1,14 +1,27 @@
 #[cfg(feature = "mock")]
 mod mock_tests {
+    use anything_core::QueryError;
     use indexify::{
-        get_num_docs, index_add, index_commit, index_delete, index_files, index_search,
+        SearchGeneration, catch_up, get_num_docs, index_add, index_commit, index_delete,
+        index_files, index_search, index_search_cancellable, index_search_request, reconcile_files,
     };
-    use std::{fs, thread};
+    use protocol::{SearchHit, SearchOptions, SearchRequest, Sort, SortKey};
+    use std::sync::{Mutex, MutexGuard};
+    use std::time::Duration;
+    use std::{fs, path::Path, thread};
     use tempfile::TempDir;
-    use vaultify::Vaultify;
+    use vaultify::{VAULTIFY, Vaultify};
+
+    /// The tests share one index and check its document count.
+    static INDEX: Mutex<()> = Mutex::new(());
+
+    fn lock_index() -> MutexGuard<'static, ()> {
+        INDEX.lock().unwrap_or_else(|e| e.into_inner())
+    }
 
     #[test]
     fn test_workflow() {
+        let _index = lock_index();
         Vaultify::init_vault();
 
         let (temp_folder, folder_num) =
@@ -19,7 +32,8 @@ mod mock_tests {
 
         let remain_exclude_path: Vec<String> = vec!["None".to_string()];
 
-        index_files(temp_folder, &remain_exclude_path);
+        let mut count_total = 0;
+        index_files(temp_folder, &remain_exclude_path, &mut count_total);
         thread::sleep(std::time::Duration::from_millis(500));
 
         assert_eq!(get_num_docs(), 77);
@@ -49,28 +63,182 @@ mod mock_tests {
         thread::sleep(std::time::Duration::from_millis(500));
         assert_eq!(get_num_docs(), 73);
 
-        let search_results = index_search("生产");
+        let search_results = index_search("生产").unwrap();
         assert!(!search_results.is_empty());
 
-        let search_results = index_search("Draft");
+        let search_results = index_search("Draft").unwrap();
         assert!(!search_results.is_empty());
 
+        let search_results = index_search("cv ext:pdf;docx").unwrap();
+        assert_eq!(search_results.len(), 1);
+
+        let search_results = index_search("cv !ext:pdf").unwrap();
+        assert!(search_results.is_empty());
+
+        assert!(index_search("size:>lots").is_err());
+
+        let search_results = index_search("repo").unwrap();
+        assert!(
+            search_results
+                .iter()
+                .any(|item| item.name == "quarterly-sales-report.xlsx")
+        );
+
+        let search_results = index_search("notes").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "notes.md");
+
+        let search_results = index_search(r"regex:^[a-z]+_\d{8}\.sql$").unwrap();
+        assert_eq!(search_results.len(), 1);
+        assert_eq!(search_results.get(0).unwrap().name, "backup_20250529.sql");
+
+        let search_results = index_search(r"regex:/db\.sql$").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "db.sql");
+
+        let search_results = index_search(r"regex:\bv2\b").unwrap();
+        assert_eq!(search_results.len(), 1);
+        assert_eq!(search_results.get(0).unwrap().name, "Logo Design v2.ai");
+
+        assert!(index_search("regex:[unclosed").is_err());
+
+        let search_results = index_search("*.SQL").unwrap();
+        assert_eq!(search_results.len(), 2);
+
+        let search_results = index_search("financial-budget-202?.csv").unwrap();
+        assert_eq!(search_results.len(), 1);
+
+        let names = |query: &str| {
+            let mut names: Vec<String> = index_search(query)
+                .unwrap()
+                .into_iter()
+                .map(|item| item.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names("regex:Beach|Chapter"),
+            vec!["PhotoShoot_Beach2025.jpg", "Thesis Chapter 3.txt"]
+        );
+        assert_eq!(names(r"regex:^cv|db\.sql$"), vec!["cv.pdf", "db.sql"]);
+
+        let page = |query: &str, sort: Sort, offset: usize, limit: usize| {
+            index_search_request(&SearchRequest {
+                id: 7,
+                query: query.to_string(),
+                sort,
+                offset,
+                limit,
+                options: SearchOptions::default(),
+            })
+        };
+        let first_page = page("ext:md", Sort::default(), 0, 2);
+        assert_eq!(first_page.id, 7);
+        assert_eq!(first_page.hits.len(), 2);
+        assert!(first_page.total > 2);
+        let second_page = page("ext:md", Sort::default(), 2, 2);
+        assert_eq!(second_page.total, first_page.total);
+        assert!(
+            second_page
+                .hits
+                .iter()
+                .all(|item| first_page.hits.iter().all(|first| first.path != item.path))
+        );
+        let everything = page("ext:md", Sort::default(), 1, 1_000_000_000);
+        assert_eq!(everything.hits.len(), first_page.total - 1);
+        let past_end = page("ext:md", Sort::default(), 1_000_000_000, 10);
+        assert!(past_end.hits.is_empty());
+        assert_eq!(past_end.total, first_page.total);
+        let overflow = page("ext:md", Sort::default(), usize::MAX, 2);
+        assert_eq!(overflow.error, Some(QueryError::PageOutOfRange.to_string()));
+
+        let response = page("size:>lots", Sort::default(), 0, 10);
+        assert!(response.hits.is_empty());
+        assert!(response.error.is_some());
+
+        let response = index_search_request(&SearchRequest {
+            query: "Draft".to_string(),
+            options: SearchOptions {
+                match_case: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(response.hits.len(), 1);
+        assert_eq!(
+            response.hits.get(0).unwrap().name,
+            "Literature Review Draft.docx"
+        );
+
+        let generation = SearchGeneration::default();
+        let superseded = generation.advance();
+        let latest = generation.advance();
+        let request = SearchRequest {
+            query: r"regex:\bv2\b".to_string(),
+            ..Default::default()
+        };
+        let response = index_search_cancellable(&request, &superseded);
+        assert!(response.hits.is_empty());
+        assert_eq!(response.error, Some(QueryError::Cancelled.to_string()));
+        assert_eq!(index_search_cancellable(&request, &latest).hits.len(), 1);
+
+        let by_name = |descending| Sort {
+            key: SortKey::Name,
+            descending,
+        };
+        let response = page("ext:sql", by_name(false), 0, 10);
+        let names: Vec<String> = response
+            .hits
+            .iter()
+            .map(|item| item.name.to_string())
+            .collect();
+        assert_eq!(names, vec!["backup_20250529.sql", "db.sql"]);
+        let response = page("ext:sql", by_name(true), 0, 10);
+        assert_eq!(response.hits.get(0).unwrap().name, "db.sql");
+
+        fs::write(
+            format!("{}/{}", temp_folder, "large_dump.sql"),
+            vec![0u8; 4096],
+        )
+        .unwrap();
+        index_add(format!("{}/{}", temp_folder, "large_dump.sql").as_str()).unwrap();
+        index_commit().unwrap();
+        thread::sleep(std::time::Duration::from_millis(500));
+        let by_size = |descending| Sort {
+            key: SortKey::Size,
+            descending,
+        };
+        let response = page("ext:sql", by_size(true), 0, 10);
+        assert_eq!(response.hits.get(0).unwrap().name, "large_dump.sql");
+        let response = page("ext:sql", by_size(false), 0, 10);
+        assert_eq!(response.hits.last().unwrap().name, "large_dump.sql");
+        index_delete(format!("{}/{}", temp_folder, "large_dump.sql").as_str()).unwrap();
+        index_commit().unwrap();
+        thread::sleep(std::time::Duration::from_millis(500));
+
+        let search_results = index_search("fuzzy:composiion").unwrap();
+        assert_eq!(
+            search_results.get(0).unwrap().name,
+            "music_composition_draft.mp3"
+        );
+
         index_add(format!("{}/{}", temp_folder, "原神.pdf").as_str()).unwrap();
         index_add(format!("{}/{}", temp_folder, "genshin.pdf").as_str()).unwrap();
         index_commit().unwrap();
         thread::sleep(std::time::Duration::from_millis(500));
         assert_eq!(get_num_docs(), 75);
 
-        let search_results = index_search("genshin");
+        let search_results = index_search("genshin").unwrap();
         assert_eq!(
             search_results.get(0).unwrap().path,
-            format!("{}/{}", temp_folder, "genshin.pdf")
+            Path::new(temp_folder).join("genshin.pdf")
         );
+        assert_eq!(search_results.get(0).unwrap().class, "pdf");
+        assert_eq!(search_results.get(0).unwrap().size, 0);
 
-        let search_results = index_search("原神");
+        let search_results = index_search("原神").unwrap();
         assert_eq!(
             search_results.get(0).unwrap().path,
-            format!("{}/{}", temp_folder, "原神.pdf")
+            Path::new(temp_folder).join("原神.pdf")
         );
 
         let dupicate_path = format!("{}/{}", temp_folder, "rust_duplicate.pdf");
@@ -86,6 +254,218 @@ mod mock_tests {
         index_commit().unwrap();
         thread::sleep(std::time::Duration::from_millis(500));
         assert_eq!(get_num_docs(), 75);
+
+        index_add(format!("{}/{}", temp_folder, "Résumé.pdf").as_str()).unwrap();
+        index_add(format!("{}/{}", temp_folder, "readme.md").as_str()).unwrap();
+        index_commit().unwrap();
+        thread::sleep(std::time::Duration::from_millis(500));
+
+        let search_results = index_search("resume").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "Résumé.pdf");
+
+        let search_results = index_search("README").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "readme.md");
+
+        assert!(index_search("case:README").unwrap().is_empty());
+        assert_eq!(index_search("case:Résumé").unwrap().len(), 1);
+
+        index_add(format!("{}/{}", temp_folder, "购物清单.md").as_str()).unwrap();
+        index_commit().unwrap();
+        thread::sleep(std::time::Duration::from_millis(500));
+
+        let search_results = index_search("gouwuqingdan").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "购物清单.md");
+
+        let search_results = index_search("gwqd").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "购物清单.md");
+
+        let search_results = index_search("qingdan").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "购物清单.md");
+
+        VAULTIFY
+            .record_run(format!("{}/{}", temp_folder, "购物清单.md").as_str())
+            .unwrap();
+        let search_results = index_search("runcount:>=1").unwrap();
+        assert_eq!(search_results.len(), 1);
+        assert_eq!(search_results.get(0).unwrap().name, "购物清单.md");
+
+        let search_results = index_search("dr:today").unwrap();
+        assert_eq!(search_results.get(0).unwrap().name, "购物清单.md");
+
+        let search_results = index_search("runcount:0 ext:md").unwrap();
+        assert!(!search_results.is_empty());
+        assert!(search_results.iter().all(|item| item.name != "购物清单.md"));
+
+        for _ in 0..2 {
+            VAULTIFY
+                .record_run(format!("{}/{}", temp_folder, "notes.md").as_str())
+                .unwrap();
+        }
+        let by_runs = |key, descending| Sort { key, descending };
+        let response = page("ext:md", by_runs(SortKey::RunCount, true), 0, 100);
+        let runs: Vec<(&str, u64)> = response
+            .hits
+            .iter()
+            .take(3)
+            .map(|item| (item.name.as_str(), item.run_count))
+            .collect();
+        assert_eq!(runs[..2], [("notes.md", 2), ("购物清单.md", 1)]);
+        assert_eq!(runs[2].1, 0);
+        let response = page("ext:md", by_runs(SortKey::RunCount, false), 0, 100);
+        assert_eq!(response.hits.last().unwrap().name, "notes.md");
+        let response = page("ext:md", by_runs(SortKey::DateRun, true), 0, 100);
+        assert!(response.hits[0].date_run > 0);
+        assert!(response.hits[1].date_run > 0);
+        assert_eq!(response.hits[2].date_run, 0);
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let _index = lock_index();
+        Vaultify::init_vault();
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().to_str().unwrap();
+        let file = |name: &str| temp_dir.path().join(name);
+        for name in [
+            "reconcile_unchanged.txt",
+            "reconcile_modified.txt",
+            "reconcile_deleted.txt",
+            "reconcile_duplicate.txt",
+        ] {
+            fs::write(file(name), "a").unwrap();
+        }
+        let docs_before = get_num_docs();
+        index_files(dir, &vec![], &mut 0);
+        index_add(file("reconcile_duplicate.txt").to_str().unwrap()).unwrap();
+        index_commit().unwrap();
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(get_num_docs(), docs_before + 6);
+        assert_eq!(hits_of(&file("reconcile_duplicate.txt")).len(), 2);
+
+        // a rewrite that keeps the modification time goes unnoticed
+        let unchanged = file("reconcile_unchanged.txt");
+        let modified_at = fs::metadata(&unchanged).unwrap().modified().unwrap();
+        fs::write(&unchanged, "abcd").unwrap();
+        set_modified(&unchanged, modified_at);
+        let modified = file("reconcile_modified.txt");
+        fs::write(&modified, "abcd").unwrap();
+        set_modified(&modified, modified_at + Duration::from_secs(10));
+        fs::remove_file(file("reconcile_deleted.txt")).unwrap();
+
+        reconcile_files(dir, &vec![]).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(get_num_docs(), docs_before + 4);
+        let sizes =
+            |path: &Path| -> Vec<u64> { hits_of(path).iter().map(|hit| hit.size).collect() };
+        assert_eq!(sizes(&unchanged), vec![1]);
+        assert_eq!(sizes(&modified), vec![4]);
+        assert!(hits_of(&file("reconcile_deleted.txt")).is_empty());
+        assert_eq!(sizes(&file("reconcile_duplicate.txt")), vec![1]);
+
+        forget(&temp_dir);
+    }
+
+    #[test]
+    fn test_catch_up() {
+        let _index = lock_index();
+        Vaultify::init_vault();
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().to_str().unwrap();
+        let path = |name: &str| temp_dir.path().join(name);
+        for folder in ["sub", "sub/nested", "sub/gone_dir"] {
+            fs::create_dir(path(folder)).unwrap();
+        }
+        for name in [
+            "sub/catch_up_kept.txt",
+            "sub/catch_up_old_name.txt",
+            "sub/catch_up_gone.txt",
+            "sub/nested/catch_up_inner.txt",
+            "sub/gone_dir/catch_up_gone_inner.txt",
+        ] {
+            fs::write(path(name), "a").unwrap();
+        }
+        let docs_before = get_num_docs();
+        index_files(dir, &vec![], &mut 0);
+        thread::sleep(Duration::from_millis(500));
+        // a second early, as file times may trail the clock
+        let since = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+            - 1;
+
+        fs::write(path("sub/catch_up_new.txt"), "a").unwrap();
+        fs::create_dir(path("sub/new_dir")).unwrap();
+        fs::write(path("sub/new_dir/catch_up_new_inner.txt"), "a").unwrap();
+        fs::rename(
+            path("sub/catch_up_old_name.txt"),
+            path("sub/catch_up_new_name.txt"),
+        )
+        .unwrap();
+        fs::remove_file(path("sub/catch_up_gone.txt")).unwrap();
+        fs::remove_dir_all(path("sub/gone_dir")).unwrap();
+        fs::write(path("sub/nested/catch_up_added.txt"), "a").unwrap();
+
+        catch_up(since, &[dir], &vec![]).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        let on_disk = walk(temp_dir.path());
+        for entry in &on_disk {
+            assert_eq!(hits_of(entry).len(), 1, "{}", entry.display());
+        }
+        assert_eq!(get_num_docs(), docs_before + 1 + on_disk.len() as u64);
+        for gone in [
+            "sub/catch_up_old_name.txt",
+            "sub/catch_up_gone.txt",
+            "sub/gone_dir/catch_up_gone_inner.txt",
+        ] {
+            assert!(hits_of(&path(gone)).is_empty(), "{}", gone);
+        }
+
+        forget(&temp_dir);
+    }
+
+    /// The indexed documents of the file at `path`.
+    fn hits_of(path: &Path) -> Vec<SearchHit> {
+        let name = path.file_name().unwrap().to_str().unwrap();
+        index_search(name)
+            .unwrap()
+            .into_iter()
+            .filter(|hit| hit.path == path)
+            .collect()
+    }
+
+    fn set_modified(path: &Path, time: std::time::SystemTime) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    /// Removes the documents of `temp_dir` and everything under it.
+    fn forget(temp_dir: &TempDir) {
+        let dir = temp_dir.path().to_str().unwrap();
+        index_delete(dir).unwrap();
+        for entry in walk(temp_dir.path()) {
+            index_delete(entry.to_str().unwrap()).unwrap();
+        }
+        index_commit().unwrap();
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    fn walk(dir: &Path) -> Vec<std::path::PathBuf> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                paths.extend(walk(&path));
+            }
+            paths.push(path);
+        }
+        paths
     }
 
     fn generate_mock_files() -> Result<(TempDir, usize), Box<dyn std::error::Error>> {
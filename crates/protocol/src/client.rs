@@ -0,0 +1,118 @@
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    ClientMessage, IndexStatus, Mount, MountPolicy, SearchRequest, SearchResponse, ServerMessage,
+};
+
+/// Writes `message` as one JSON line.
+pub fn write_message<W: Write, T: Serialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+/// Reads the next JSON line, or `None` once the peer has closed the stream.
+pub fn read_message<R: BufRead, T: DeserializeOwned>(reader: &mut R) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// Talks to the daemon listening on `socket`, opening a connection per call.
+#[derive(Debug, Clone)]
+pub struct Client {
+    socket: PathBuf,
+}
+
+impl Client {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Client {
+            socket: socket.into(),
+        }
+    }
+
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    pub fn connect(&self) -> io::Result<UnixStream> {
+        UnixStream::connect(&self.socket)
+    }
+
+    /// Sends `message` and waits for the first reply.
+    pub fn call(&self, message: &ClientMessage) -> io::Result<ServerMessage> {
+        let mut stream = self.connect()?;
+        write_message(&mut stream, message)?;
+        match read_message(&mut io::BufReader::new(stream))? {
+            Some(ServerMessage::Error { message }) => Err(io::Error::other(message)),
+            Some(reply) => Ok(reply),
+            None => Err(ErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    pub fn search(&self, request: SearchRequest) -> io::Result<SearchResponse> {
+        match self.call(&ClientMessage::Search(request))? {
+            ServerMessage::Search(response) => Ok(response),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn status(&self) -> io::Result<IndexStatus> {
+        match self.call(&ClientMessage::Status)? {
+            ServerMessage::Status(status) => Ok(status),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn record_run(&self, path: &str) -> io::Result<()> {
+        let message = ClientMessage::RecordRun {
+            path: path.to_string(),
+        };
+        match self.call(&message)? {
+            ServerMessage::Ok => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn mounts(&self) -> io::Result<Vec<Mount>> {
+        match self.call(&ClientMessage::Mounts)? {
+            ServerMessage::Mounts { mounts } => Ok(mounts),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn set_mount_policy(&self, mount_point: &Path, policy: MountPolicy) -> io::Result<()> {
+        let message = ClientMessage::SetMountPolicy {
+            mount_point: mount_point.to_path_buf(),
+            policy,
+        };
+        match self.call(&message)? {
+            ServerMessage::Ok => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    /// Asks the daemon to bring the index up to date and waits until it has,
+    /// which can take as long as building it.
+    pub fn refresh(&self) -> io::Result<()> {
+        match self.call(&ClientMessage::Refresh)? {
+            ServerMessage::Ok => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+fn unexpected(reply: ServerMessage) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("unexpected reply: {:?}", reply),
+    )
+}
@@ -0,0 +1,175 @@
+//! Messages between the indexing daemon and its clients.
+//!
+//! The daemon listens on a Unix domain socket named [`SOCKET_FILE_NAME`] in
+//! the app's config directory. Both sides write one JSON object per line,
+//! tagged by `type`. A client may keep the connection open and send any number
+//! of messages:
+//!
+//! ```text
+//! > {"type":"search","id":1,"query":"report ext:pdf","limit":20}
+//! < {"type":"search","id":1,"hits":[{"name":"report.pdf","path":"/home/me/report.pdf","class":"pdf","size":1024,"modified":1749600000,"is_dir":false,"run_count":2,"date_run":1749700000}],"total":1,"elapsed":{"secs":0,"nanos":1200000},"error":null}
+//! > {"type":"status"}
+//! < {"type":"status","indexed":true,"indexed_files":120345,"indexed_progress":100.0}
+//! > {"type":"record_run","path":"/home/me/report.pdf"}
+//! < {"type":"ok"}
+//! > {"type":"mounts"}
+//! < {"type":"mounts","mounts":[{"mount_point":"/mnt/nas","fs_type":"nfs4","kind":"network","policy":"skip"}]}
+//! > {"type":"set_mount_policy","mount_point":"/mnt/nas","policy":"index-without-watching"}
+//! < {"type":"ok"}
+//! > {"type":"refresh"}
+//! < {"type":"ok"}
+//! ```
+//!
+//! Omitted search fields take the defaults of [`SearchRequest`], and `sort`
+//! keys are `relevance`, `kind`, `name`, `path`, `size`, `modified`,
+//! `run_count` or `date_run`.
+//! Searches are answered asynchronously: a newer search on the same
+//! connection cancels an older one still running, whose response is never
+//! sent. Mount policies are `index`, `skip` or `index-without-watching` and
+//! take effect when the index is next built. `refresh` brings the index up to
+//! date and is answered once it is, while searches on the connection go on
+//! being answered. A line that cannot be handled is answered with `{"type":"error","message":"..."}`.
+
+mod client;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+pub use anything_core::{Mount, MountKind, MountPolicy, SearchHit, SearchOptions, Sort, SortKey};
+pub use client::{Client, read_message, write_message};
+
+pub const DEFAULT_LIMIT: usize = 100;
+pub const SOCKET_FILE_NAME: &str = "anything.sock";
+pub const LOCK_FILE_NAME: &str = "anything.lock";
+
+/// Asks for `limit` hits of `query` in `sort` order, starting at `offset`.
+/// The `id` is echoed in the response so clients can drop stale answers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchRequest {
+    pub id: u64,
+    pub query: String,
+    pub sort: Sort,
+    pub offset: usize,
+    pub limit: usize,
+    pub options: SearchOptions,
+}
+
+impl Default for SearchRequest {
+    fn default() -> Self {
+        SearchRequest {
+            id: 0,
+            query: String::new(),
+            sort: Sort::default(),
+            offset: 0,
+            limit: DEFAULT_LIMIT,
+            options: SearchOptions::default(),
+        }
+    }
+}
+
+/// The answer to the request with the same `id`. `total` counts every hit of
+/// the query, not only the returned page. A failed search has no hits and
+/// carries the reason in `error`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub id: u64,
+    pub hits: Vec<SearchHit>,
+    pub total: usize,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+/// Progress of the index build, as shown in the title bar.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct IndexStatus {
+    pub indexed: bool,
+    pub indexed_files: u64,
+    pub indexed_progress: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Search(SearchRequest),
+    Status,
+    /// Counts an open of `path` for ranking and the `runcount:` modifier.
+    RecordRun {
+        path: String,
+    },
+    Mounts,
+    SetMountPolicy {
+        mount_point: PathBuf,
+        policy: MountPolicy,
+    },
+    Refresh,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Search(SearchResponse),
+    Status(IndexStatus),
+    Mounts { mounts: Vec<Mount> },
+    Ok,
+    Error { message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_lines() {
+        let message: ClientMessage =
+            serde_json::from_str(r#"{"type":"search","id":3,"query":"cv","sort":{"key":"size"}}"#)
+                .unwrap();
+        assert_eq!(
+            message,
+            ClientMessage::Search(SearchRequest {
+                id: 3,
+                query: "cv".to_string(),
+                sort: Sort {
+                    key: SortKey::Size,
+                    descending: false,
+                },
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<Sort>(r#"{"key":"run_count","descending":true}"#).unwrap(),
+            Sort {
+                key: SortKey::RunCount,
+                descending: true,
+            }
+        );
+
+        assert_eq!(
+            serde_json::to_string(&ServerMessage::Status(IndexStatus {
+                indexed: true,
+                indexed_files: 42,
+                indexed_progress: 100.0,
+            }))
+            .unwrap(),
+            r#"{"type":"status","indexed":true,"indexed_files":42,"indexed_progress":100.0}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"record_run","path":"/a"}"#).unwrap(),
+            ClientMessage::RecordRun {
+                path: "/a".to_string()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(
+                r#"{"type":"set_mount_policy","mount_point":"/mnt/nas","policy":"index-without-watching"}"#
+            )
+            .unwrap(),
+            ClientMessage::SetMountPolicy {
+                mount_point: PathBuf::from("/mnt/nas"),
+                policy: MountPolicy::IndexWithoutWatching,
+            }
+        );
+    }
+}
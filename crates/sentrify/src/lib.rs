@@ -6,12 +6,16 @@ use notify::{
 };
 use std::{
     path::Path,
+    sync::atomic::{AtomicBool, Ordering},
     thread::{self},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tracing::{Level, debug, span, trace, warn};
 use vaultify::VAULTIFY;
 
+/// Set while file events are applied to the index as they come.
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
 pub fn init_service() {
     thread::spawn(move || {
         let span = span!(Level::DEBUG, "sentry service thread");
@@ -19,20 +23,41 @@ pub fn init_service() {
         if let Err(e) = guard(VAULTIFY.get("default_include_path").unwrap()) {
             warn!("guard error: {e:?}")
         }
+        WATCHING.store(false, Ordering::SeqCst);
     });
 }
 
+/// Commits pending changes and records now as the time the index has followed
+/// the disk up to, for the next start to catch up from. Does nothing unless
+/// file events are being applied, so a start after a gap catches up on it.
+pub fn record_last_seen() -> Result<()> {
+    if !WATCHING.load(Ordering::SeqCst) {
+        debug!("file monitoring is not running, keeping the last seen time");
+        return Ok(());
+    }
+    index_commit()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    VAULTIFY.set("last_seen", now.to_string())?;
+    debug!("recorded last seen time {}", now);
+    Ok(())
+}
+
 pub fn guard<P: AsRef<Path>>(path: P) -> Result<()> {
     let (event_sender, event_receiver) = unbounded::<Result<Event, Error>>();
     let mut watcher = RecommendedWatcher::new(event_sender, Config::default())?;
     watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
-    let default_exclude_paths =
+    let mut default_exclude_paths =
         serde_json::from_str::<Vec<String>>(VAULTIFY.get("default_exclude_path").unwrap().as_str())
             .unwrap();
+    default_exclude_paths.extend(
+        indexify::unwatched_mount_points()
+            .iter()
+            .map(|path| path.to_string_lossy().to_string()),
+    );
 
     loop {
         if let Ok(indexed_value) = VAULTIFY.get("indexed") {
-            if indexed_value.as_str() == "true" {
+            if indexed_value.as_str() == "true" && !indexify::is_catching_up() {
                 debug!("indexing complete, starting file monitoring");
                 break;
             }
@@ -40,6 +65,7 @@ pub fn guard<P: AsRef<Path>>(path: P) -> Result<()> {
         debug!("waiting for indexing to complete");
         thread::sleep(Duration::from_secs(2));
     }
+    WATCHING.store(true, Ordering::SeqCst);
 
     let mut count = 0;
     for res in event_receiver {
@@ -56,30 +82,31 @@ pub fn guard<P: AsRef<Path>>(path: P) -> Result<()> {
                             trace!("index skip: {}", path_str);
                             continue;
                         }
+                        // a reconcile walk or catch-up may have indexed it already
                         count += 1;
+                        index_delete(path_str)?;
                         index_add(path_str)?;
                     }
                 }
-                EventKind::Modify(kind) => {
-                    if let ModifyKind::Name(_) = kind {
-                        for path in event.paths {
-                            let path_str = path.to_str().unwrap();
-
-                            if default_exclude_paths
-                                .iter()
-                                .any(|exclude| path_str.starts_with(exclude))
-                            {
-                                trace!("index skip: {}", path_str);
-                                continue;
-                            }
-
-                            if Path::new(path_str).exists() {
-                                count += 1;
-                                index_add(path_str)?;
-                            } else {
-                                count += 1;
-                                index_delete(path_str)?;
-                            }
+                EventKind::Modify(
+                    ModifyKind::Name(_) | ModifyKind::Data(_) | ModifyKind::Metadata(_),
+                ) => {
+                    for path in event.paths {
+                        let path_str = path.to_str().unwrap();
+
+                        if default_exclude_paths
+                            .iter()
+                            .any(|exclude| path_str.starts_with(exclude))
+                        {
+                            trace!("index skip: {}", path_str);
+                            continue;
+                        }
+
+                        // replaces any document already held for the path
+                        count += 1;
+                        index_delete(path_str)?;
+                        if Path::new(path_str).exists() {
+                            index_add(path_str)?;
                         }
                     }
                 }
@@ -160,7 +187,7 @@ mod tests {
             doc_count
         );
 
-        let search_results = index_search("4");
+        let search_results = index_search("4").unwrap();
         assert!(
             !search_results.is_empty(),
             "Search should return results for indexed files"
@@ -189,7 +216,7 @@ mod tests {
         );
         thread::sleep(Duration::from_millis(1000));
 
-        let search_results_renamed = index_search("303");
+        let search_results_renamed = index_search("303").unwrap();
         assert!(
             !search_results_renamed.is_empty(),
             "Search should return results for renamed files"
@@ -208,7 +235,7 @@ mod tests {
         index_commit().unwrap();
         thread::sleep(Duration::from_millis(1000));
 
-        let search_results_after_delete = index_search("15");
+        let search_results_after_delete = index_search("15").unwrap();
         let doc_count = get_num_docs();
         let _ = index_list();
         assert_eq!(doc_count, 0);
@@ -1,9 +1,16 @@
-use std::{fs, path::Path, vec};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    vec,
+};
 
 use anyhow::Result;
 use directories::{ProjectDirs, UserDirs};
-use once_cell::sync::Lazy;
-use redb::{Database, Error, ReadableTable, TableDefinition};
+use once_cell::sync::{Lazy, OnceCell};
+use redb::{Database, Error, ReadableTable, TableDefinition, TableError};
 #[cfg(feature = "mock")]
 use tempfile::{NamedTempFile, tempdir};
 use tracing::{debug, info};
@@ -12,23 +19,38 @@ const APP_NAME: &str = "Anything";
 const DB_FILE_NAME: &str = "anything.redb";
 const TANTIVY_DIR_NAME: &str = "tantivy";
 const TABLE_NAME: &str = "anything";
+const RUNS_TABLE_NAME: &str = "runs";
+/// Bumped when a release changes the defaults stored in the vault.
+const VAULT_VERSION: &str = "2";
 
-pub static VAULTIFY: Lazy<Vaultify> = Lazy::new(|| {
+pub static VAULTIFY: Lazy<&'static Vaultify> = Lazy::new(|| {
     #[cfg(feature = "mock")]
     {
-        Vaultify::new().expect("Failed to initialize mock Vaultify")
+        Vaultify::open().expect("Failed to initialize mock Vaultify")
     }
     #[cfg(not(feature = "mock"))]
     {
-        Vaultify::new().expect("Failed to initialize Vaultify")
+        Vaultify::open().expect("Failed to initialize Vaultify")
     }
 });
 
+static OPENED: OnceCell<Vaultify> = OnceCell::new();
+
 pub struct Vaultify {
     config_file: String,
     tantivy_path: String,
     db: Database,
     table_def: TableDefinition<'static, &'static str, String>,
+    runs_def: TableDefinition<'static, &'static str, (u64, i64)>,
+    /// The runs table as last read, dropped whenever a run is recorded.
+    runs: Mutex<Option<Arc<HashMap<String, RunInfo>>>>,
+}
+
+/// How often and when a path was last opened from the app.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunInfo {
+    pub count: u64,
+    pub last_run: i64,
 }
 
 impl Vaultify {
@@ -57,12 +79,16 @@ impl Vaultify {
 
         let db: Database = Database::create(config_file.clone())?;
         let table_def: TableDefinition<'_, &'static str, String> = TableDefinition::new(TABLE_NAME);
+        let runs_def: TableDefinition<'_, &'static str, (u64, i64)> =
+            TableDefinition::new(RUNS_TABLE_NAME);
 
         Ok(Vaultify {
             config_file,
             tantivy_path,
             db,
             table_def,
+            runs_def,
+            runs: Mutex::default(),
         })
     }
 
@@ -87,9 +113,22 @@ impl Vaultify {
         Ok((config_file, tantivy_path, config_path))
     }
 
+    /// Opens the vault behind `VAULTIFY`, failing instead of panicking when
+    /// another process holds the database.
+    pub fn open() -> Result<&'static Vaultify> {
+        OPENED.get_or_try_init(Self::new)
+    }
+
+    /// Directory holding the vault and the daemon's socket and lock file.
+    pub fn config_dir() -> Result<PathBuf> {
+        let (_, _, config_path) = Self::get_directories()?;
+        Ok(PathBuf::from(config_path))
+    }
+
     pub fn init_vault() {
         if VAULTIFY.get("config_file").is_ok() {
             info!("Vault already initialized");
+            Self::migrate().expect("Failed to migrate vault");
             return;
         }
 
@@ -101,6 +140,51 @@ impl Vaultify {
         Self::init_config().expect("Failed to initialize vault configuration");
     }
 
+    /// Brings a vault written by an older release up to date. The default
+    /// excludes are merged into the stored ones, which the user may have edited,
+    /// and a refresh drops what the index holds under the new ones.
+    fn migrate() -> Result<()> {
+        let stored_version = VAULTIFY.get("vault_version").unwrap_or_default();
+        if stored_version == VAULT_VERSION {
+            return Ok(());
+        }
+        info!(
+            "migrating vault from version '{}' to {}",
+            stored_version, VAULT_VERSION
+        );
+        let mut exclude_paths: Vec<String> = VAULTIFY
+            .get("default_exclude_path")
+            .ok()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+        for path in Self::default_exclude_path()? {
+            if !exclude_paths.contains(&path) {
+                exclude_paths.push(path);
+            }
+        }
+        VAULTIFY.batch_set(&[
+            (
+                "default_exclude_path",
+                &serde_json::to_string(&exclude_paths)?,
+            ),
+            ("vault_version", VAULT_VERSION),
+            ("refresh", "true"),
+        ])
+    }
+
+    #[cfg(not(feature = "mock"))]
+    fn default_exclude_path() -> Result<Vec<String>> {
+        let user_dirs =
+            UserDirs::new().ok_or_else(|| anyhow::anyhow!("Failed to get user directories"))?;
+        let (_, _, config_path) = Vaultify::get_directories()?;
+        Ok(Self::default_exclude_paths(&user_dirs, &config_path))
+    }
+
+    #[cfg(feature = "mock")]
+    fn default_exclude_path() -> Result<Vec<String>> {
+        Ok(vec!["None".to_string()])
+    }
+
     #[cfg(not(feature = "mock"))]
     fn init_config() -> Result<()> {
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -108,16 +192,6 @@ impl Vaultify {
         let user_dirs =
             UserDirs::new().ok_or_else(|| anyhow::anyhow!("Failed to get user directories"))?;
         let home_dir = user_dirs.home_dir().to_string_lossy().to_string();
-        let music_dir = user_dirs
-            .audio_dir()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get audio directory"))?
-            .to_str()
-            .expect("Failed to convert path to string with music_dir");
-        let picture_dir: &str = user_dirs
-            .picture_dir()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get picture directory"))?
-            .to_str()
-            .expect("Failed to convert path to string with picture_dir");
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)?
             .as_secs()
@@ -133,24 +207,61 @@ impl Vaultify {
         VAULTIFY.set("indexed_files", "0".to_string())?;
         VAULTIFY.set("indexed_progress", "0.0".to_string())?;
         VAULTIFY.set("version", env!("CARGO_PKG_VERSION").to_string())?;
+        VAULTIFY.set("vault_version", VAULT_VERSION.to_string())?;
         VAULTIFY.set(
             "default_exclude_path",
-            serde_json::to_string(&vec![
-                "/System",
-                "/bin",
-                "/dev",
-                "/sbin",
-                "/lib",
-                "/private",
-                "/.VolumeIcon.icns",
-                music_dir,
-                picture_dir,
-                config_path.as_str(),
-            ])?,
+            serde_json::to_string(&Self::default_exclude_paths(&user_dirs, &config_path))?,
         )?;
         Ok(())
     }
 
+    /// macOS system folders and the media libraries, which minimal setups may
+    /// not have.
+    #[cfg(all(not(feature = "mock"), target_os = "macos"))]
+    fn default_exclude_paths(user_dirs: &UserDirs, config_path: &str) -> Vec<String> {
+        let mut paths = vec![
+            "/System",
+            "/bin",
+            "/dev",
+            "/sbin",
+            "/lib",
+            "/private",
+            "/.VolumeIcon.icns",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+        paths.extend(
+            [user_dirs.audio_dir(), user_dirs.picture_dir()]
+                .into_iter()
+                .flatten()
+                .map(|dir| dir.to_string_lossy().to_string()),
+        );
+        paths.push(config_path.to_string());
+        paths
+    }
+
+    /// The kernel's pseudo filesystems, runtime state and snap mounts, and the
+    /// index itself.
+    #[cfg(all(not(feature = "mock"), target_os = "linux"))]
+    fn default_exclude_paths(_: &UserDirs, config_path: &str) -> Vec<String> {
+        let mut paths = vec!["/proc", "/sys", "/dev", "/run", "/snap"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        paths.push(VAULTIFY.tantivy_path.clone());
+        paths.push(config_path.to_string());
+        paths
+    }
+
+    #[cfg(all(
+        not(feature = "mock"),
+        not(any(target_os = "macos", target_os = "linux"))
+    ))]
+    fn default_exclude_paths(_: &UserDirs, config_path: &str) -> Vec<String> {
+        vec![VAULTIFY.tantivy_path.clone(), config_path.to_string()]
+    }
+
     #[cfg(feature = "mock")]
     fn init_config() -> Result<()> {
         let user_dirs =
@@ -165,9 +276,10 @@ impl Vaultify {
         VAULTIFY.set("indexed_files", "0".to_string())?;
         VAULTIFY.set("indexed_progress", "0.0".to_string())?;
         VAULTIFY.set("version", env!("CARGO_PKG_VERSION").to_string())?;
+        VAULTIFY.set("vault_version", VAULT_VERSION.to_string())?;
         VAULTIFY.set(
             "default_exclude_path",
-            serde_json::to_string(&vec!["None"])?,
+            serde_json::to_string(&Self::default_exclude_path()?)?,
         )?;
         Ok(())
     }
@@ -206,6 +318,45 @@ impl Vaultify {
         Ok(())
     }
 
+    /// Increments the open count of `path` and stamps its last run time.
+    pub fn record_run(&self, path: &str) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(self.runs_def)?;
+            let count = table.get(path)?.map(|entry| entry.value().0).unwrap_or(0);
+            table.insert(path, (count + 1, now))?;
+        }
+        write_txn.commit()?;
+        self.runs.lock().unwrap().take();
+        Ok(())
+    }
+
+    /// The run history by path, read once and kept until the next run is
+    /// recorded.
+    pub fn list_runs(&self) -> Result<Arc<HashMap<String, RunInfo>>> {
+        let mut cached = self.runs.lock().unwrap();
+        if let Some(runs) = cached.as_ref() {
+            return Ok(runs.clone());
+        }
+        let read_txn = self.db.begin_read()?;
+        let mut runs = HashMap::new();
+        match read_txn.open_table(self.runs_def) {
+            Ok(table) => {
+                for entry in table.iter()? {
+                    let (key, value) = entry?;
+                    let (count, last_run) = value.value();
+                    runs.insert(key.value().to_string(), RunInfo { count, last_run });
+                }
+            }
+            Err(TableError::TableDoesNotExist(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+        let runs = Arc::new(runs);
+        *cached = Some(runs.clone());
+        Ok(runs)
+    }
+
     pub fn cleanup(path: String) {
         if Path::new(&path).exists() {
             fs::remove_dir_all(&path).expect("Failed to remove: directory");
@@ -244,5 +395,36 @@ mod tests {
     #[cfg(feature = "mock")]
     fn test_vaultify() {
         Vaultify::init_vault();
+        assert_eq!(VAULTIFY.get("vault_version").unwrap(), VAULT_VERSION);
+
+        VAULTIFY
+            .batch_set(&[
+                ("default_exclude_path", r#"["/old"]"#),
+                ("vault_version", "1"),
+                ("refresh", "false"),
+            ])
+            .unwrap();
+        Vaultify::init_vault();
+        assert_eq!(
+            VAULTIFY.get("default_exclude_path").unwrap(),
+            r#"["/old","None"]"#
+        );
+        assert_eq!(VAULTIFY.get("vault_version").unwrap(), VAULT_VERSION);
+        assert_eq!(VAULTIFY.get("refresh").unwrap(), "true");
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_record_run() {
+        VAULTIFY.record_run("/tmp/report.pdf").unwrap();
+        VAULTIFY.record_run("/tmp/report.pdf").unwrap();
+
+        let runs = VAULTIFY.list_runs().unwrap();
+        let run = runs["/tmp/report.pdf"];
+        assert_eq!(run.count, 2);
+        assert!(run.last_run > 0);
+
+        VAULTIFY.record_run("/tmp/report.pdf").unwrap();
+        assert_eq!(VAULTIFY.list_runs().unwrap()["/tmp/report.pdf"].count, 3);
     }
 }
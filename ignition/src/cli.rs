@@ -0,0 +1,308 @@
+use std::io::{self, BufWriter, ErrorKind, Write};
+
+use anyhow::{Result, bail};
+use protocol::{SearchHit, SearchRequest, SearchResponse, Sort, SortKey};
+use serde_json::json;
+use tracing::debug;
+use vaultify::Vaultify;
+
+use crate::daemon;
+
+/// Exit code when the search found something.
+pub const EXIT_FOUND: i32 = 0;
+/// Exit code when the search ran but matched nothing.
+pub const EXIT_NO_RESULTS: i32 = 1;
+/// Exit code for invalid arguments, a missing index or a failed search.
+pub const EXIT_ERROR: i32 = 2;
+
+const USAGE: &str = "\
+Usage: anything [options] [search text]
+
+Searches the index built by Anything, like Everything's es.exe. The search
+text accepts the same modifiers as the search box, e.g. ext:pdf size:>1mb.
+
+Options:
+  -r, -regex <pattern>       match names, or paths with a '/', by regex
+  -i, -case                  match case
+  -p, -match-path            match the search text against full paths
+  -path <path>               limit results to files and folders under path
+  -parent <path>             limit results to the direct children of path
+  /ad                        folders only
+  /a-d                       files only
+  -n, -max-results <num>     print at most num results (default 100)
+  -o, -offset <num>          skip the first num results
+  -s                         sort by path
+  -sort <column>[-ascending|-descending]
+                             sort by name, path, size, extension,
+                             date-modified, run-count or date-run
+  -sort-ascending            sort in ascending order
+  -sort-descending           sort in descending order
+  -0                         separate results with NUL instead of newlines
+  -json                      print results as JSON
+  -get-result-count          print the number of results only
+  -h, -help                  show this help
+  --daemon                   run the indexer in the background and serve
+                             searches to the window and to this command
+  --daemon --http <address>  also answer Everything's HTTP search API on
+                             address, or a port on localhost, or 'off'
+  --daemon --http-auth <user>:<password>
+                             require basic auth over HTTP, or 'off'; the
+                             password is kept unencrypted in the settings
+
+Exit status is 0 when something was found, 1 when nothing matched and 2 on
+errors.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Format {
+    #[default]
+    Lines,
+    Nul,
+    Json,
+    Count,
+}
+
+#[derive(Debug, PartialEq, Default)]
+struct Args {
+    request: SearchRequest,
+    format: Format,
+    help: bool,
+}
+
+/// Runs a search described by es.exe-style arguments and returns the exit
+/// code.
+pub fn run(args: Vec<String>) -> i32 {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("anything: {}", e);
+            eprintln!("Try 'anything -help' for more information.");
+            return EXIT_ERROR;
+        }
+    };
+    if args.help {
+        print!("{}", USAGE);
+        return EXIT_FOUND;
+    }
+    let response = match search(&args.request) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("anything: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+    if let Some(error) = response.error {
+        eprintln!("anything: {}", error);
+        return EXIT_ERROR;
+    }
+    if let Err(e) = print_hits(&response.hits, response.total, args.format)
+        && e.kind() != ErrorKind::BrokenPipe
+    {
+        eprintln!("anything: {}", e);
+        return EXIT_ERROR;
+    }
+    if response.total == 0 {
+        EXIT_NO_RESULTS
+    } else {
+        EXIT_FOUND
+    }
+}
+
+/// Asks the daemon when one is running and reads the index directly
+/// otherwise.
+fn search(request: &SearchRequest) -> Result<SearchResponse> {
+    match daemon::client()?.search(request.clone()) {
+        Ok(response) => return Ok(response),
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            // a starting daemon holds the vault before it listens
+            if daemon::is_running()? {
+                bail!("the daemon is starting, try again in a moment");
+            }
+            debug!("no daemon running, reading the index directly: {}", e);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Vaultify::open()?;
+    indexify::open_index_read_only()?;
+    Ok(indexify::index_search_request(request))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut parsed = Args::default();
+    let mut terms = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| match args.next() {
+            Some(value) => Ok(value),
+            None => bail!("option '{}' requires a value", name),
+        };
+        match arg.as_str() {
+            "-r" | "-regex" => terms.push(format!("regex:{}", quote(&value(&arg)?))),
+            "-i" | "-case" => parsed.request.options.match_case = true,
+            "-p" | "-match-path" => parsed.request.options.match_path = true,
+            "-path" => {
+                let path = value(&arg)?;
+                let pattern = format!("^{}/", regex::escape(path.trim_end_matches('/')));
+                terms.push(format!("regex:{}", quote(&pattern)));
+            }
+            "-parent" => terms.push(format!("parent:{}", quote(&value(&arg)?))),
+            "/ad" => terms.push("folder:".to_string()),
+            "/a-d" => terms.push("file:".to_string()),
+            "-n" | "-max-results" => parsed.request.limit = parse_number(&arg, value(&arg)?)?,
+            "-o" | "-offset" => parsed.request.offset = parse_number(&arg, value(&arg)?)?,
+            "-s" => parsed.request.sort.key = SortKey::Path,
+            "-sort" => parsed.request.sort = parse_sort(&value(&arg)?)?,
+            "-sort-ascending" => parsed.request.sort.descending = false,
+            "-sort-descending" => parsed.request.sort.descending = true,
+            "-0" => parsed.format = Format::Nul,
+            "-json" => parsed.format = Format::Json,
+            "-get-result-count" => parsed.format = Format::Count,
+            "-h" | "-help" | "--help" => parsed.help = true,
+            option if option.starts_with('-') && option.len() > 1 => {
+                bail!("unknown option '{}'", option)
+            }
+            _ => terms.push(quote(&arg)),
+        }
+    }
+    parsed.request.query = terms.join(" ");
+    Ok(parsed)
+}
+
+/// Keeps an argument the shell passed as one word a single search term.
+fn quote(text: &str) -> String {
+    if text.contains(|c: char| c.is_whitespace() || c == '"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn parse_number(option: &str, value: String) -> Result<usize> {
+    match value.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => bail!("option '{}' expects a number, got '{}'", option, value),
+    }
+}
+
+fn parse_sort(value: &str) -> Result<Sort> {
+    let (column, descending) = if let Some(column) = value.strip_suffix("-descending") {
+        (column, true)
+    } else {
+        (value.strip_suffix("-ascending").unwrap_or(value), false)
+    };
+    let key = match column {
+        "name" => SortKey::Name,
+        "path" => SortKey::Path,
+        "size" => SortKey::Size,
+        "extension" | "ext" => SortKey::Kind,
+        "date-modified" | "dm" => SortKey::Modified,
+        "run-count" => SortKey::RunCount,
+        "date-run" | "dr" => SortKey::DateRun,
+        _ => bail!("cannot sort by '{}'", value),
+    };
+    Ok(Sort { key, descending })
+}
+
+fn print_hits(hits: &[SearchHit], total: usize, format: Format) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    match format {
+        Format::Lines => {
+            for hit in hits {
+                writeln!(out, "{}", hit.path.display())?;
+            }
+        }
+        Format::Nul => {
+            for hit in hits {
+                write!(out, "{}\0", hit.path.display())?;
+            }
+        }
+        Format::Json => {
+            let results: Vec<_> = hits
+                .iter()
+                .map(|hit| {
+                    json!({
+                        "name": hit.name,
+                        "path": hit.path,
+                        "class": hit.class,
+                        "size": hit.size,
+                        "modified": hit.modified,
+                        "is_dir": hit.is_dir,
+                        "run_count": hit.run_count,
+                        "date_run": hit.date_run,
+                    })
+                })
+                .collect();
+            let output = json!({ "total": total, "results": results });
+            writeln!(out, "{}", output)?;
+        }
+        Format::Count => writeln!(out, "{}", total)?,
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&[
+            "-i",
+            "-n",
+            "5",
+            "-sort",
+            "size-descending",
+            "-json",
+            "report",
+            "ext:pdf",
+        ])
+        .unwrap();
+        assert_eq!(args.request.query, "report ext:pdf");
+        assert!(args.request.options.match_case);
+        assert_eq!(args.request.limit, 5);
+        assert_eq!(
+            args.request.sort,
+            Sort {
+                key: SortKey::Size,
+                descending: true
+            }
+        );
+        assert_eq!(args.format, Format::Json);
+
+        let args = parse(&["-r", r"^\d+ copy$", "/ad", "-path", "/home/my docs/", "-0"]).unwrap();
+        assert_eq!(
+            args.request.query,
+            r#"regex:"^\d+ copy$" folder: regex:"^/home/my docs/""#
+        );
+        assert_eq!(args.format, Format::Nul);
+
+        let args = parse(&["-s", "-sort-descending", "annual report"]).unwrap();
+        assert_eq!(args.request.query, "\"annual report\"");
+        assert_eq!(args.request.sort.key, SortKey::Path);
+        assert!(args.request.sort.descending);
+
+        let args = parse(&["a \"b c", "say\"hi\""]).unwrap();
+        assert_eq!(args.request.query, r#""a ""b c" "say""hi""""#);
+
+        let args = parse(&["-sort", "run-count-descending"]).unwrap();
+        assert_eq!(
+            args.request.sort,
+            Sort {
+                key: SortKey::RunCount,
+                descending: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse(&["-n"]).is_err());
+        assert!(parse(&["-n", "many"]).is_err());
+        assert!(parse(&["-sort", "colour"]).is_err());
+        assert!(parse(&["-unknown"]).is_err());
+    }
+}
@@ -0,0 +1,399 @@
+#[cfg(feature = "gui")]
+use std::env;
+use std::fs::{self, File, TryLockError};
+use std::io::{BufReader, ErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(feature = "gui")]
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process;
+#[cfg(feature = "gui")]
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+#[cfg(feature = "gui")]
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+use async_signal::{Signal, Signals};
+use protocol::{
+    Client, ClientMessage, IndexStatus, LOCK_FILE_NAME, SOCKET_FILE_NAME, SearchRequest,
+    SearchResponse, ServerMessage, read_message, write_message,
+};
+#[cfg(feature = "gui")]
+use smol::channel::{Receiver, Sender};
+use smol::stream::StreamExt;
+use tracing::{debug, error, info, warn};
+use vaultify::{VAULTIFY, Vaultify};
+
+use crate::http;
+#[cfg(feature = "dbus")]
+use crate::search_provider;
+
+pub const DAEMON_FLAG: &str = "--daemon";
+
+#[cfg(feature = "gui")]
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs the indexer and the file monitor without a window and serves clients
+/// on the socket until the process is stopped.
+pub fn run(args: &[String]) -> Result<()> {
+    let config_dir = Vaultify::config_dir()?;
+    fs::create_dir_all(&config_dir)?;
+    let _lock = lock(&config_dir.join(LOCK_FILE_NAME))?;
+
+    Vaultify::init_vault();
+    save_options(args)?;
+    indexify::init_service()?;
+    sentrify::init_service();
+    let signals = Signals::new([Signal::Term, Signal::Int])?;
+    thread::spawn(move || stop_on(signals));
+
+    if let Ok(address) = VAULTIFY.get("http_address")
+        && !address.is_empty()
+    {
+        let credentials = VAULTIFY.get("http_credentials").ok().and_then(|value| {
+            let (username, password) = value.split_once(':')?;
+            Some((username.to_string(), password.to_string()))
+        });
+        if let Err(e) = http::spawn(&address, credentials) {
+            error!("failed to start http server on {}: {}", address, e);
+        }
+    }
+    #[cfg(feature = "dbus")]
+    let _search_providers = search_provider::serve()
+        .inspect_err(|e| warn!("search providers are unavailable: {}", e))
+        .ok();
+
+    let socket = config_dir.join(SOCKET_FILE_NAME);
+    // only the lock holder binds, so a socket file left behind is stale
+    if socket.exists() {
+        fs::remove_file(&socket)?;
+    }
+    let listener = UnixListener::bind(&socket)?;
+    info!("daemon listening on {}", socket.display());
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream) {
+                        warn!("client connection failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("failed to accept client: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Exits once the daemon is told to stop.
+fn stop_on(mut signals: Signals) {
+    let signal = smol::block_on(signals.next());
+    info!("stopping on {:?}", signal);
+    exit();
+}
+
+/// Commits the index and records how far it has followed the disk before
+/// exiting, so the next start only catches up on later changes.
+fn exit() -> ! {
+    if let Err(e) = indexify::index_commit() {
+        error!("failed to commit the index: {}", e);
+    }
+    if let Err(e) = sentrify::record_last_seen() {
+        error!("failed to record the last seen time: {}", e);
+    }
+    process::exit(0);
+}
+
+/// Saves the `--http` and `--http-auth` options in the vault, so a daemon the
+/// window starts later serves HTTP the same way. The credentials are stored in
+/// plain text, readable by anyone who can read the vault.
+fn save_options(args: &[String]) -> Result<()> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            bail!("option '{}' requires a value", arg);
+        };
+        match arg.as_str() {
+            "--http" => {
+                let address = match value.as_str() {
+                    "off" => String::new(),
+                    port if port.parse::<u16>().is_ok() => format!("127.0.0.1:{}", port),
+                    address => address.to_string(),
+                };
+                VAULTIFY.set("http_address", address)?;
+            }
+            "--http-auth" => {
+                let credentials = match value.as_str() {
+                    "off" => String::new(),
+                    credentials if credentials.contains(':') => credentials.to_string(),
+                    _ => bail!("option '{}' expects <user>:<password>", arg),
+                };
+                VAULTIFY.set("http_credentials", credentials)?;
+            }
+            _ => bail!("unknown daemon option '{}'", arg),
+        }
+    }
+    Ok(())
+}
+
+/// Takes the lock file that keeps a second indexer from starting. The lock is
+/// held until the returned file is dropped.
+fn lock(path: &Path) -> Result<File> {
+    let mut file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => bail!("another daemon holds {}", path.display()),
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+    file.set_len(0)?;
+    writeln!(file, "{}", process::id())?;
+    Ok(file)
+}
+
+fn handle(stream: UnixStream) -> Result<()> {
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let mut reader = BufReader::new(stream);
+    let (request_sender, request_reciver) = smol::channel::unbounded::<SearchRequest>();
+    let (data_sender, data_reciver) = smol::channel::unbounded::<SearchResponse>();
+    indexify::serve(request_reciver, data_sender);
+
+    let search_writer = writer.clone();
+    thread::spawn(move || {
+        while let Ok(response) = data_reciver.recv_blocking() {
+            if let Err(e) = reply(&search_writer, &ServerMessage::Search(response)) {
+                debug!("client left before its results: {}", e);
+                break;
+            }
+        }
+    });
+
+    loop {
+        let message = match read_message::<_, ClientMessage>(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => {
+                reply(
+                    &writer,
+                    &ServerMessage::Error {
+                        message: e.to_string(),
+                    },
+                )?;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let response = match message {
+            ClientMessage::Search(request) => {
+                request_sender.try_send(request)?;
+                continue;
+            }
+            ClientMessage::Status => ServerMessage::Status(index_status()),
+            ClientMessage::RecordRun { path } => match VAULTIFY.record_run(&path) {
+                Ok(()) => ServerMessage::Ok,
+                Err(e) => ServerMessage::Error {
+                    message: e.to_string(),
+                },
+            },
+            ClientMessage::Mounts => ServerMessage::Mounts {
+                mounts: indexify::mounts(),
+            },
+            ClientMessage::SetMountPolicy {
+                mount_point,
+                policy,
+            } => match indexify::set_mount_policy(&mount_point, policy) {
+                Ok(()) => ServerMessage::Ok,
+                Err(e) => ServerMessage::Error {
+                    message: e.to_string(),
+                },
+            },
+            ClientMessage::Refresh => {
+                info!("refresh requested, reconciling the index");
+                // the walk takes minutes, so the connection keeps serving
+                let refresh_writer = writer.clone();
+                thread::spawn(move || {
+                    let response = match indexify::refresh_index() {
+                        Ok(()) => ServerMessage::Ok,
+                        Err(e) => {
+                            error!("failed to refresh the index: {}", e);
+                            ServerMessage::Error {
+                                message: e.to_string(),
+                            }
+                        }
+                    };
+                    if let Err(e) = reply(&refresh_writer, &response) {
+                        debug!("client left before the refresh was done: {}", e);
+                    }
+                });
+                continue;
+            }
+        };
+        reply(&writer, &response)?;
+    }
+    Ok(())
+}
+
+fn reply(writer: &Mutex<UnixStream>, message: &ServerMessage) -> std::io::Result<()> {
+    write_message(&mut *writer.lock().unwrap(), message)
+}
+
+fn index_status() -> IndexStatus {
+    IndexStatus {
+        indexed: VAULTIFY.get("indexed").is_ok_and(|value| value == "true"),
+        indexed_files: VAULTIFY
+            .get("indexed_files")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0),
+        indexed_progress: VAULTIFY
+            .get("indexed_progress")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0.0),
+    }
+}
+
+/// A client for the daemon's socket. Connecting fails when no daemon runs.
+pub fn client() -> Result<Client> {
+    Ok(Client::new(Vaultify::config_dir()?.join(SOCKET_FILE_NAME)))
+}
+
+/// Whether a daemon holds the lock file, which it takes before opening the
+/// vault and long before it listens on the socket.
+pub fn is_running() -> Result<bool> {
+    let file = match File::open(Vaultify::config_dir()?.join(LOCK_FILE_NAME)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    match file.try_lock_shared() {
+        Ok(()) => Ok(false),
+        Err(TryLockError::WouldBlock) => Ok(true),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Connects to the running daemon, starting one in the background first when
+/// none answers.
+#[cfg(feature = "gui")]
+pub fn connect_or_spawn() -> Result<Client> {
+    let client = client()?;
+    if client.status().is_ok() {
+        return Ok(client);
+    }
+
+    info!(
+        "no daemon answers on {}, starting one",
+        client.socket().display()
+    );
+    let mut daemon = spawn()?;
+    let start = Instant::now();
+    while start.elapsed() < STARTUP_TIMEOUT {
+        thread::sleep(Duration::from_millis(100));
+        if client.status().is_ok() {
+            return Ok(client);
+        }
+        // a daemon still committing on its way out holds the lock, so the new
+        // one gives up; try again until the old one is gone
+        if daemon.try_wait()?.is_some() {
+            daemon = spawn()?;
+        }
+    }
+    bail!("daemon did not start within {:?}", STARTUP_TIMEOUT)
+}
+
+#[cfg(feature = "gui")]
+fn spawn() -> Result<Child> {
+    let daemon = Command::new(env::current_exe()?)
+        .arg(DAEMON_FLAG)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    Ok(daemon)
+}
+
+/// Relays the window's search requests to the daemon over one connection and
+/// hands the responses back. When the daemon goes away it reconnects, starting
+/// a daemon when none answers, and resends the request still waiting for
+/// results.
+#[cfg(feature = "gui")]
+pub fn forward(
+    client: &Client,
+    request_reciver: Receiver<SearchRequest>,
+    data_sender: Sender<SearchResponse>,
+) -> Result<()> {
+    let stream = client.connect()?;
+    let link = Arc::new(Mutex::new(Link {
+        writer: stream.try_clone()?,
+        pending: None,
+    }));
+    let request_link = link.clone();
+    thread::spawn(move || {
+        while let Ok(request) = request_reciver.recv_blocking() {
+            let mut link = request_link.lock().unwrap();
+            link.pending = Some(request.clone());
+            // the reader resends it once it has reconnected
+            if let Err(e) = write_message(&mut link.writer, &ClientMessage::Search(request)) {
+                warn!("failed to send search request: {}", e);
+            }
+        }
+    });
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        loop {
+            match read_message(&mut reader) {
+                Ok(Some(ServerMessage::Search(response))) => {
+                    link.lock()
+                        .unwrap()
+                        .pending
+                        .take_if(|request| request.id == response.id);
+                    if let Err(e) = data_sender.try_send(response) {
+                        error!("Failed to send results: {}", e);
+                        break;
+                    }
+                    continue;
+                }
+                Ok(Some(message)) => {
+                    warn!("unexpected message from daemon: {:?}", message);
+                    continue;
+                }
+                Ok(None) => warn!("daemon closed the connection, reconnecting"),
+                Err(e) => warn!("failed to read from daemon, reconnecting: {}", e),
+            }
+            match reconnect(&link) {
+                Ok(stream) => reader = BufReader::new(stream),
+                Err(e) => {
+                    error!("failed to reconnect to the daemon: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// The connection `forward` writes to and the last request it has not seen
+/// results for.
+#[cfg(feature = "gui")]
+struct Link {
+    writer: UnixStream,
+    pending: Option<SearchRequest>,
+}
+
+#[cfg(feature = "gui")]
+fn reconnect(link: &Mutex<Link>) -> Result<UnixStream> {
+    let stream = connect_or_spawn()?.connect()?;
+    let mut link = link.lock().unwrap();
+    link.writer = stream.try_clone()?;
+    if let Some(request) = link.pending.clone() {
+        write_message(&mut link.writer, &ClientMessage::Search(request))?;
+    }
+    Ok(stream)
+}
@@ -0,0 +1,452 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use protocol::{SearchHit, SearchRequest, SearchResponse, SortKey};
+use serde_json::{Map, Value, json};
+use tracing::{debug, info, warn};
+
+/// Seconds between the Unix and the Windows FILETIME epoch.
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+const FILETIME_TICKS_PER_SECOND: i64 = 10_000_000;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest request or header line read, like common servers' 8 KiB.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// Columns Everything adds to each JSON result on request.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Columns {
+    path: bool,
+    size: bool,
+    date_modified: bool,
+}
+
+#[derive(Debug, PartialEq, Default)]
+struct Params {
+    request: SearchRequest,
+    json: bool,
+    columns: Columns,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    headers: Vec<String>,
+    body: String,
+}
+
+/// Answers Everything's HTTP search URLs such as
+/// `/?search=report&json=1&path_column=1&sort=size&ascending=0` on `address`
+/// from a background thread. With credentials every request needs them as
+/// basic auth.
+pub fn spawn(address: &str, credentials: Option<(String, String)>) -> Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let local = listener.local_addr()?;
+    if !local.ip().is_loopback() {
+        warn!("http server on {} is reachable from other hosts", local);
+    }
+    info!("http server listening on {}", local);
+
+    let authorization = Arc::new(credentials.map(|(username, password)| {
+        format!(
+            "Basic {}",
+            BASE64_STANDARD.encode(format!("{}:{}", username, password))
+        )
+    }));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let authorization = authorization.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = handle(stream, authorization.as_deref()) {
+                            debug!("http connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("failed to accept http client: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, authorization: Option<&str>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(request_line) = read_line(&mut reader)? else {
+        return Response::text("400 Bad Request", "request line too long")
+            .write(&mut stream, false);
+    };
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+
+    let mut given = None;
+    for count in 0.. {
+        let header = match read_line(&mut reader)? {
+            // the blank line ending the headers may follow the last allowed one
+            Some(header) if count <= MAX_HEADERS => header,
+            _ => {
+                return Response::text("431 Request Header Fields Too Large", "headers too large")
+                    .write(&mut stream, false);
+            }
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("authorization")
+        {
+            given = Some(value.trim().to_string());
+        }
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let response = if method != "GET" && method != "HEAD" {
+        Response::text("405 Method Not Allowed", "only GET is supported")
+    } else if authorization.is_some_and(|expected| !authorized(given.as_deref(), expected)) {
+        let mut response = Response::text("401 Unauthorized", "authorization required");
+        response
+            .headers
+            .push("WWW-Authenticate: Basic realm=\"Anything\"".to_string());
+        response
+    } else if path != "/" {
+        Response::text("404 Not Found", "not found")
+    } else {
+        match parse_params(query) {
+            Ok(params) => search(params),
+            Err(e) => Response::text("400 Bad Request", &e.to_string()),
+        }
+    };
+    response.write(&mut stream, method == "HEAD")
+}
+
+/// Reads one line, or `None` when it is longer than `MAX_LINE_LENGTH`. An
+/// empty string means the client closed the connection.
+fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_LENGTH).read_line(&mut line)?;
+    if line.len() as u64 == MAX_LINE_LENGTH && !line.ends_with('\n') {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+fn search(params: Params) -> Response {
+    let response = indexify::index_search_request(&params.request);
+    if let Some(error) = response.error {
+        return Response::text("400 Bad Request", &error);
+    }
+    if params.json {
+        Response {
+            status: "200 OK",
+            content_type: "application/json; charset=utf-8",
+            headers: vec![],
+            body: to_json(&response, params.columns).to_string(),
+        }
+    } else {
+        Response {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            headers: vec![],
+            body: to_html(&params.request.query, &response),
+        }
+    }
+}
+
+/// Reads Everything's query string. Its `wholeword` and `diacritics`
+/// switches, and sort orders with no counterpart here, are ignored.
+fn parse_params(query: &str) -> Result<Params> {
+    let mut params = Params::default();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value)?;
+        let enabled = value != "0";
+        match decode(name)?.as_str() {
+            "search" | "s" | "q" => params.request.query = value,
+            "offset" | "o" => params.request.offset = parse_number(name, &value)?,
+            "count" | "c" => params.request.limit = parse_number(name, &value)?,
+            "json" | "j" => params.json = enabled,
+            "case" | "i" => params.request.options.match_case = enabled,
+            "path" | "p" => params.request.options.match_path = enabled,
+            "regex" | "r" => params.request.options.regex = enabled,
+            "path_column" => params.columns.path = enabled,
+            "size_column" => params.columns.size = enabled,
+            "date_modified_column" => params.columns.date_modified = enabled,
+            "sort" => {
+                params.request.sort.key = match value.as_str() {
+                    "name" => SortKey::Name,
+                    "path" => SortKey::Path,
+                    "size" => SortKey::Size,
+                    "extension" => SortKey::Kind,
+                    "date_modified" => SortKey::Modified,
+                    "run_count" => SortKey::RunCount,
+                    "date_run" => SortKey::DateRun,
+                    _ => {
+                        debug!("cannot sort by '{}', keeping the default order", value);
+                        SortKey::default()
+                    }
+                }
+            }
+            "ascending" => params.request.sort.descending = !enabled,
+            _ => {}
+        }
+    }
+    Ok(params)
+}
+
+/// Compares the given authorization header against the expected one in time
+/// that does not depend on where they first differ.
+fn authorized(given: Option<&str>, expected: &str) -> bool {
+    let given = given.unwrap_or_default().as_bytes();
+    let expected = expected.as_bytes();
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize> {
+    match value.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => bail!("parameter '{}' expects a number, got '{}'", name, value),
+    }
+}
+
+/// Undoes the percent and `+` encoding of a query string component.
+fn decode(text: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next(), input.next()];
+                let value = match hex {
+                    [Some(high), Some(low)] => std::str::from_utf8(&[high, low])
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+                    _ => None,
+                };
+                match value {
+                    Some(value) => bytes.push(value),
+                    None => bail!("invalid percent encoding in '{}'", text),
+                }
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Shapes the hits like Everything's JSON: `path` is the parent folder, and
+/// `size` and `date_modified`, a FILETIME, are strings.
+fn to_json(response: &SearchResponse, columns: Columns) -> Value {
+    let results: Vec<Value> = response
+        .hits
+        .iter()
+        .map(|hit| {
+            let mut result = Map::new();
+            let kind = if hit.is_dir { "folder" } else { "file" };
+            result.insert("type".into(), kind.into());
+            result.insert("name".into(), hit.name.clone().into());
+            if columns.path {
+                result.insert("path".into(), parent(hit).into());
+            }
+            if columns.size {
+                result.insert("size".into(), hit.size.to_string().into());
+            }
+            if columns.date_modified {
+                let filetime = (hit.modified + FILETIME_EPOCH_OFFSET) * FILETIME_TICKS_PER_SECOND;
+                result.insert("date_modified".into(), filetime.to_string().into());
+            }
+            Value::Object(result)
+        })
+        .collect();
+    json!({ "totalResults": response.total, "results": results })
+}
+
+fn to_html(query: &str, response: &SearchResponse) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{} - Anything</title></head><body>\n<p>{} results</p>\n<table>\n<tr><th>Name</th><th>Path</th><th>Size</th></tr>\n",
+        escape(query),
+        response.total
+    );
+    for hit in &response.hits {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape(&hit.name),
+            escape(&parent(hit)),
+            if hit.is_dir {
+                String::new()
+            } else {
+                hit.size.to_string()
+            }
+        ));
+    }
+    html.push_str("</table>\n</body></html>\n");
+    html
+}
+
+fn parent(hit: &SearchHit) -> String {
+    hit.path
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Response {
+    fn text(status: &'static str, body: &str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            headers: vec![],
+            body: format!("{}\n", body),
+        }
+    }
+
+    fn write(&self, stream: &mut TcpStream, head_only: bool) -> io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        );
+        for header in &self.headers {
+            head.push_str(header);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        if !head_only {
+            stream.write_all(self.body.as_bytes())?;
+        }
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use protocol::Sort;
+
+    use super::*;
+
+    #[test]
+    fn test_read_line() {
+        let mut reader = io::Cursor::new("GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(
+            read_line(&mut reader).unwrap().as_deref(),
+            Some("GET / HTTP/1.1\r\n")
+        );
+        assert_eq!(read_line(&mut reader).unwrap().as_deref(), Some("\r\n"));
+        assert_eq!(read_line(&mut reader).unwrap().as_deref(), Some(""));
+
+        let long = format!(
+            "GET /?search={} HTTP/1.1\r\n",
+            "a".repeat(MAX_LINE_LENGTH as usize)
+        );
+        assert_eq!(read_line(&mut io::Cursor::new(long)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = parse_params(
+            "search=annual+report%20ext%3Apdf&json=1&path_column=1&size_column=1&sort=size&ascending=0&count=5&offset=10&i=1",
+        )
+        .unwrap();
+        assert_eq!(params.request.query, "annual report ext:pdf");
+        assert!(params.json);
+        assert_eq!(
+            params.columns,
+            Columns {
+                path: true,
+                size: true,
+                date_modified: false
+            }
+        );
+        assert_eq!(
+            params.request.sort,
+            Sort {
+                key: SortKey::Size,
+                descending: true
+            }
+        );
+        assert_eq!(params.request.limit, 5);
+        assert_eq!(params.request.offset, 10);
+        assert!(params.request.options.match_case);
+
+        assert!(parse_params("count=many").is_err());
+        assert_eq!(
+            parse_params("sort=run_count").unwrap().request.sort.key,
+            SortKey::RunCount
+        );
+        assert_eq!(
+            parse_params("sort=date_created").unwrap().request.sort.key,
+            SortKey::Relevance
+        );
+        assert!(parse_params("s=%zz").is_err());
+    }
+
+    #[test]
+    fn test_authorized() {
+        assert!(authorized(Some("Basic YTpi"), "Basic YTpi"));
+        assert!(!authorized(Some("Basic YTpj"), "Basic YTpi"));
+        assert!(!authorized(Some("Basic YTpiYg=="), "Basic YTpi"));
+        assert!(!authorized(None, "Basic YTpi"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let response = SearchResponse {
+            id: 0,
+            total: 1,
+            hits: vec![SearchHit {
+                name: "report.pdf".to_string(),
+                path: PathBuf::from("/home/docs/report.pdf"),
+                class: "pdf".to_string(),
+                size: 2048,
+                modified: 0,
+                is_dir: false,
+                run_count: 0,
+                date_run: 0,
+            }],
+            elapsed: Duration::ZERO,
+            error: None,
+        };
+        let columns = Columns {
+            path: true,
+            size: true,
+            date_modified: true,
+        };
+        assert_eq!(
+            to_json(&response, columns),
+            json!({
+                "totalResults": 1,
+                "results": [{
+                    "type": "file",
+                    "name": "report.pdf",
+                    "path": "/home/docs",
+                    "size": "2048",
+                    "date_modified": "116444736000000000",
+                }]
+            })
+        );
+    }
+}
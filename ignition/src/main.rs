@@ -1,28 +1,52 @@
-use anyhow::{Ok, Result};
-use facade::component::anything_item::Something;
+mod cli;
+mod daemon;
+mod http;
+#[cfg(feature = "dbus")]
+mod search_provider;
+
+use std::{env, process};
+
+use anyhow::Result;
+#[cfg(feature = "gui")]
+use protocol::{SearchRequest, SearchResponse};
+#[cfg(feature = "gui")]
 use smol::channel::{Receiver, Sender};
+#[cfg(feature = "gui")]
 use tracing::info;
-use vaultify::Vaultify;
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == daemon::DAEMON_FLAG) {
+        logger::init_log();
+        return daemon::run(&args[1..]);
+    }
+    #[cfg(feature = "gui")]
+    if args.is_empty() {
+        return launch();
+    }
+    process::exit(cli::run(args));
+}
+
+#[cfg(feature = "gui")]
+fn launch() -> Result<()> {
     logger::init_log();
-    Vaultify::init_vault();
+    let client = daemon::connect_or_spawn()?;
     let (request_sender, request_reciver, data_sender, data_reciver) = init_channel();
-    indexify::init_service(request_reciver, data_sender)?;
-    sentrify::init_service();
-    facade::setup(request_sender, data_reciver);
+    daemon::forward(&client, request_reciver, data_sender)?;
+    facade::setup(client, request_sender, data_reciver);
 
     Ok(())
 }
 
+#[cfg(feature = "gui")]
 fn init_channel() -> (
-    Sender<String>,
-    Receiver<String>,
-    Sender<Vec<Something>>,
-    Receiver<Vec<Something>>,
+    Sender<SearchRequest>,
+    Receiver<SearchRequest>,
+    Sender<SearchResponse>,
+    Receiver<SearchResponse>,
 ) {
-    let (request_sender, request_reciver) = smol::channel::unbounded::<String>();
-    let (data_sender, data_reciver) = smol::channel::unbounded::<Vec<Something>>();
+    let (request_sender, request_reciver) = smol::channel::unbounded::<SearchRequest>();
+    let (data_sender, data_reciver) = smol::channel::unbounded::<SearchResponse>();
     info!("channel initialized");
     (request_sender, request_reciver, data_sender, data_reciver)
 }
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::{env, thread};
+
+use anyhow::Result;
+use protocol::{SearchHit, SearchRequest};
+use tracing::{debug, info, warn};
+use vaultify::VAULTIFY;
+use zbus::blocking::{Connection, connection};
+use zbus::interface;
+use zbus::zvariant::Value;
+
+pub const BUS_NAME: &str = "io.github.oops418.anything";
+const SHELL_PATH: &str = "/io/github/oops418/anything/SearchProvider";
+const RUNNER_PATH: &str = "/io/github/oops418/anything/Runner";
+/// Hits handed to the shell per search, which shows only a few of them.
+const RESULT_LIMIT: usize = 20;
+/// KRunner's `PossibleMatch` category.
+const POSSIBLE_MATCH: i32 = 30;
+const OPEN_FOLDER_ACTION: &str = "open-folder";
+
+/// Id, text, icon, category, relevance and properties of a KRunner match.
+type RunnerMatch = (
+    String,
+    String,
+    String,
+    i32,
+    f64,
+    HashMap<String, Value<'static>>,
+);
+
+/// Opens files and folders; tests swap in a command that does nothing.
+#[cfg(not(test))]
+const OPENER: &str = "xdg-open";
+#[cfg(test)]
+const OPENER: &str = "true";
+
+/// Registers the GNOME Shell and KRunner search providers on the session bus.
+/// They answer until the returned connection is dropped.
+pub fn serve() -> Result<Connection> {
+    serve_on(connection::Builder::session()?)
+}
+
+fn serve_on(builder: connection::Builder) -> Result<Connection> {
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(SHELL_PATH, ShellSearchProvider)?
+        .serve_at(RUNNER_PATH, Runner)?
+        .build()?;
+    info!(
+        "search providers registered on the session bus as {}",
+        BUS_NAME
+    );
+    Ok(connection)
+}
+
+struct ShellSearchProvider;
+
+#[interface(name = "org.gnome.Shell.SearchProvider2")]
+impl ShellSearchProvider {
+    async fn get_initial_result_set(&self, terms: Vec<String>) -> Vec<String> {
+        search_paths(terms).await
+    }
+
+    /// Searches again instead of narrowing the previous results, which would
+    /// miss hits ranked below the previous page.
+    async fn get_subsearch_result_set(
+        &self,
+        _previous_results: Vec<String>,
+        terms: Vec<String>,
+    ) -> Vec<String> {
+        search_paths(terms).await
+    }
+
+    fn get_result_metas(&self, identifiers: Vec<String>) -> Vec<HashMap<String, Value<'static>>> {
+        identifiers.iter().map(|id| result_meta(id)).collect()
+    }
+
+    async fn activate_result(&self, identifier: String, _terms: Vec<String>, _timestamp: u32) {
+        smol::unblock(move || open(&identifier)).await
+    }
+
+    /// Opens the window; it has no way to take the shell's search text yet.
+    fn launch_search(&self, _terms: Vec<String>, _timestamp: u32) {
+        let child = env::current_exe().and_then(|exe| Command::new(exe).spawn());
+        match child {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+            }
+            Err(e) => warn!("failed to open the window: {}", e),
+        }
+    }
+}
+
+struct Runner;
+
+#[interface(name = "org.kde.krunner1")]
+impl Runner {
+    fn actions(&self) -> Vec<(String, String, String)> {
+        vec![(
+            OPEN_FOLDER_ACTION.to_string(),
+            "Open Containing Folder".to_string(),
+            "document-open-folder".to_string(),
+        )]
+    }
+
+    #[zbus(name = "Match")]
+    async fn find(&self, query: String) -> Vec<RunnerMatch> {
+        let hits = smol::unblock(move || search(query)).await;
+        let count = hits.len() as f64;
+        hits.into_iter()
+            .enumerate()
+            .map(|(rank, hit)| {
+                let properties = HashMap::from([("subtext".to_string(), parent(&hit.path).into())]);
+                (
+                    hit.path.to_string_lossy().to_string(),
+                    hit.name,
+                    icon(hit.is_dir).to_string(),
+                    POSSIBLE_MATCH,
+                    1.0 - rank as f64 / count,
+                    properties,
+                )
+            })
+            .collect()
+    }
+
+    async fn run(&self, match_id: String, action_id: String) {
+        smol::unblock(move || {
+            if action_id == OPEN_FOLDER_ACTION {
+                reveal(&match_id);
+            } else {
+                open(&match_id);
+            }
+        })
+        .await
+    }
+}
+
+fn search(query: String) -> Vec<SearchHit> {
+    if query.trim().is_empty() {
+        return vec![];
+    }
+    let request = SearchRequest {
+        query,
+        limit: RESULT_LIMIT,
+        ..Default::default()
+    };
+    let response = indexify::index_search_request(&request);
+    if let Some(error) = response.error {
+        debug!("search provider query failed: {}", error);
+    }
+    response.hits
+}
+
+async fn search_paths(terms: Vec<String>) -> Vec<String> {
+    let hits = smol::unblock(move || search(terms.join(" "))).await;
+    hits.into_iter()
+        .map(|hit| hit.path.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Describes a result by its path alone, so it needs no second search.
+fn result_meta(id: &str) -> HashMap<String, Value<'static>> {
+    let path = Path::new(id);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| id.to_string());
+    HashMap::from([
+        ("id".to_string(), id.to_string().into()),
+        ("name".to_string(), name.into()),
+        ("description".to_string(), parent(path).into()),
+        ("gicon".to_string(), icon(path.is_dir()).into()),
+    ])
+}
+
+fn parent(path: &Path) -> String {
+    path.parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn icon(is_dir: bool) -> &'static str {
+    if is_dir { "folder" } else { "text-x-generic" }
+}
+
+/// Opens `path` with its default application and records the open, like the
+/// window does.
+fn open(path: &str) {
+    if let Err(e) = Command::new(OPENER).arg(path).status() {
+        warn!("failed to open {}: {}", path, e);
+        return;
+    }
+    if let Err(e) = VAULTIFY.record_run(path) {
+        warn!("failed to record run of {}: {}", path, e);
+    }
+}
+
+fn reveal(path: &str) {
+    if let Err(e) = Command::new(OPENER).arg(parent(Path::new(path))).status() {
+        warn!("failed to open the folder of {}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_meta() {
+        let meta = result_meta("/home/docs/annual report.pdf");
+        assert_eq!(meta["id"], Value::from("/home/docs/annual report.pdf"));
+        assert_eq!(meta["name"], Value::from("annual report.pdf"));
+        assert_eq!(meta["description"], Value::from("/home/docs"));
+        assert_eq!(meta["gicon"], Value::from("text-x-generic"));
+    }
+
+    /// A private bus, stopped when dropped.
+    #[cfg(feature = "mock")]
+    struct Bus(std::process::Child);
+
+    #[cfg(feature = "mock")]
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_shell_search_provider() {
+        use std::io::{BufRead, BufReader};
+        use std::process::Stdio;
+        use std::time::Duration;
+        use std::{fs, thread};
+        use vaultify::Vaultify;
+
+        let Ok(mut daemon) = Command::new("dbus-daemon")
+            .args(["--session", "--print-address", "--nofork"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            eprintln!("skipping, dbus-daemon is not installed");
+            return;
+        };
+        let stdout = daemon.stdout.take().unwrap();
+        let _bus = Bus(daemon);
+        let mut address = String::new();
+        BufReader::new(stdout).read_line(&mut address).unwrap();
+        let address = address.trim();
+
+        Vaultify::init_vault();
+        let folder = tempfile::tempdir().unwrap();
+        let report = folder.path().join("quarterly-report.pdf");
+        fs::write(&report, "report").unwrap();
+        let report = report.to_str().unwrap();
+        let mut count = 0;
+        indexify::index_files(folder.path().to_str().unwrap(), &vec![], &mut count);
+        thread::sleep(Duration::from_millis(500));
+
+        let _server = serve_on(connection::Builder::address(address).unwrap()).unwrap();
+        let client = connection::Builder::address(address)
+            .unwrap()
+            .build()
+            .unwrap();
+        let provider = zbus::blocking::Proxy::new(
+            &client,
+            BUS_NAME,
+            SHELL_PATH,
+            "org.gnome.Shell.SearchProvider2",
+        )
+        .unwrap();
+
+        let terms = vec!["quarterly".to_string()];
+        let ids: Vec<String> = provider.call("GetInitialResultSet", &(&terms,)).unwrap();
+        assert_eq!(ids, vec![report.to_string()]);
+
+        let terms = vec!["quarterly".to_string(), "pdf".to_string()];
+        let ids: Vec<String> = provider
+            .call("GetSubsearchResultSet", &(&ids, &terms))
+            .unwrap();
+        assert_eq!(ids, vec![report.to_string()]);
+
+        let metas: Vec<HashMap<String, zbus::zvariant::OwnedValue>> =
+            provider.call("GetResultMetas", &(&ids,)).unwrap();
+        assert_eq!(metas.len(), 1);
+        assert_eq!(
+            metas[0]["name"],
+            Value::from("quarterly-report.pdf").try_into().unwrap()
+        );
+
+        let () = provider
+            .call("ActivateResult", &(report, &terms, 0u32))
+            .unwrap();
+        assert_eq!(VAULTIFY.list_runs().unwrap()[report].count, 1);
+    }
+}